@@ -3,12 +3,28 @@ pub mod model;
 pub use mongodb;
 pub use mongodb::bson::doc;
 pub use mongodb::error::Error;
+use mongodb::error::{ErrorKind, WriteFailure};
+use mongodb::options::IndexOptions;
 pub use mongodb::Collection;
-use mongodb::{options::ClientOptions, Client};
+use mongodb::{options::ClientOptions, Client, IndexModel};
+use std::time::Duration;
 
+/// The error code of the duplicate key of a unique index
+const DUPLICATE_KEY_CODE: i32 = 11000;
+
+#[derive(Clone)]
 pub struct Database {
     pub client: Option<Client>,
+    pub audit_log: Option<Collection<model::audit_log::AuditLog>>,
     pub user: Option<Collection<model::auth::user::User>>,
+    pub refresh_token: Option<Collection<model::auth::token::RefreshToken>>,
+    pub revoked_token: Option<Collection<model::auth::token::RevokedToken>>,
+    pub data_export: Option<Collection<model::export::DataExport>>,
+    pub login_events: Option<Collection<model::auth::login_event::LoginEvent>>,
+    pub session: Option<Collection<model::auth::session::Session>>,
+    pub login_failure: Option<Collection<model::auth::login_failure::LoginFailure>>,
+    pub passkey_challenge: Option<Collection<model::auth::passkey_challenge::PasskeyChallenge>>,
+    pub teacher_verification: Option<Collection<model::teacher_verification::TeacherVerification>>,
 }
 
 /// Init mongodb
@@ -28,8 +44,219 @@ pub async fn init(mongodb_url: String) -> mongodb::error::Result<Database> {
         .run_command(doc! {"ping": true}, None)
         .await?;
 
+    let refresh_token = db.collection("refresh_token");
+
+    // Remove the refresh tokens when they expire
+    refresh_token
+        .create_index(ttl_index(doc! { "exp": 1 }), None)
+        .await?;
+    refresh_token
+        .create_index(unique_index(doc! { "token_hash": 1 }), None)
+        .await?;
+
+    let revoked_token = db.collection("revoked_token");
+
+    // Remove the revoked tokens when the tokens expire
+    revoked_token
+        .create_index(ttl_index(doc! { "exp": 1 }), None)
+        .await?;
+    revoked_token
+        .create_index(
+            IndexModel::builder().keys(doc! { "user_id": 1 }).build(),
+            None,
+        )
+        .await?;
+
+    let data_export = db.collection("data_export");
+
+    // Remove the data exports when they expire
+    data_export
+        .create_index(ttl_index(doc! { "exp": 1 }), None)
+        .await?;
+    data_export
+        .create_index(unique_index(doc! { "token_hash": 1 }), None)
+        .await?;
+    data_export
+        .create_index(
+            IndexModel::builder().keys(doc! { "user_id": 1 }).build(),
+            None,
+        )
+        .await?;
+
+    let login_failure = db.collection("login_failure");
+
+    // Remove the login failures when they expire
+    login_failure
+        .create_index(ttl_index(doc! { "exp": 1 }), None)
+        .await?;
+    login_failure
+        .create_index(unique_index(doc! { "key": 1 }), None)
+        .await?;
+
+    let passkey_challenge = db.collection("passkey_challenge");
+
+    // Remove the passkey challenges when they expire
+    passkey_challenge
+        .create_index(ttl_index(doc! { "exp": 1 }), None)
+        .await?;
+    passkey_challenge
+        .create_index(unique_index(doc! { "challenge": 1 }), None)
+        .await?;
+
+    let session = db.collection("session");
+
+    // Remove the sessions when they expire
+    session
+        .create_index(ttl_index(doc! { "exp": 1 }), None)
+        .await?;
+    session
+        .create_index(
+            IndexModel::builder().keys(doc! { "user_id": 1 }).build(),
+            None,
+        )
+        .await?;
+
+    let login_events = db.collection("login_events");
+
+    // Remove the login events when they expire
+    login_events
+        .create_index(ttl_index(doc! { "exp": 1 }), None)
+        .await?;
+    login_events
+        .create_index(
+            IndexModel::builder()
+                .keys(doc! { "user_id": 1, "time": -1 })
+                .build(),
+            None,
+        )
+        .await?;
+
+    let audit_log = db.collection("audit_log");
+
+    // Find the latest logs, and the logs of a user
+    audit_log
+        .create_index(
+            IndexModel::builder().keys(doc! { "time": -1 }).build(),
+            None,
+        )
+        .await?;
+    audit_log
+        .create_index(
+            IndexModel::builder()
+                .keys(doc! { "user_id": 1, "time": -1 })
+                .build(),
+            None,
+        )
+        .await?;
+
+    let teacher_verification = db.collection("teacher_verification");
+
+    // Find the requests of a user, and the pending requests for the reviewers
+    teacher_verification
+        .create_index(
+            IndexModel::builder()
+                .keys(doc! { "user_id": 1, "created_at": -1 })
+                .build(),
+            None,
+        )
+        .await?;
+    teacher_verification
+        .create_index(
+            IndexModel::builder()
+                .keys(doc! { "status": 1, "created_at": 1 })
+                .build(),
+            None,
+        )
+        .await?;
+    // A user can only have one pending request
+    teacher_verification
+        .create_index(
+            IndexModel::builder()
+                .keys(doc! { "user_id": 1 })
+                .options(
+                    IndexOptions::builder()
+                        .unique(true)
+                        .partial_filter_expression(doc! { "status": "Pending" })
+                        .build(),
+                )
+                .build(),
+            None,
+        )
+        .await?;
+
+    let user = db.collection::<model::auth::user::User>("user");
+
+    // The email can only be used by one user,
+    // the index can't be created until the users of the same email are merged, see `duplicate_emails`.
+    // The server must not start without the index.
+    if duplicate_emails(&user).await?.is_empty() {
+        user.create_index(unique_index(doc! { "email": 1 }), None)
+            .await?;
+    }
+
     Ok(Database {
         client: Some(client),
-        user: Some(db.collection("user")),
+        audit_log: Some(audit_log),
+        user: Some(user),
+        refresh_token: Some(refresh_token),
+        revoked_token: Some(revoked_token),
+        data_export: Some(data_export),
+        login_events: Some(login_events),
+        session: Some(session),
+        login_failure: Some(login_failure),
+        passkey_challenge: Some(passkey_challenge),
+        teacher_verification: Some(teacher_verification),
     })
 }
+
+/// Find the emails used by more than one user
+pub async fn duplicate_emails(
+    user: &Collection<model::auth::user::User>,
+) -> mongodb::error::Result<Vec<String>> {
+    let mut cursor = user
+        .aggregate(
+            [
+                doc! { "$group": { "_id": "$email", "count": { "$sum": 1 } } },
+                doc! { "$match": { "count": { "$gt": 1 } } },
+            ],
+            None,
+        )
+        .await?;
+
+    let mut emails = vec![];
+    while cursor.advance().await? {
+        if let Ok(email) = cursor.deserialize_current()?.get_str("_id") {
+            emails.push(email.to_string());
+        }
+    }
+
+    Ok(emails)
+}
+
+/// Check the error is caused by a duplicate key of a unique index
+pub fn is_duplicate_key_error(error: &Error) -> bool {
+    match *error.kind {
+        ErrorKind::Write(WriteFailure::WriteError(ref error)) => error.code == DUPLICATE_KEY_CODE,
+        ErrorKind::Command(ref error) => error.code == DUPLICATE_KEY_CODE,
+        _ => false,
+    }
+}
+
+/// Create a index that remove the document when the time of `keys` is reached
+fn ttl_index(keys: mongodb::bson::Document) -> IndexModel {
+    IndexModel::builder()
+        .keys(keys)
+        .options(
+            IndexOptions::builder()
+                .expire_after(Duration::from_secs(0))
+                .build(),
+        )
+        .build()
+}
+
+fn unique_index(keys: mongodb::bson::Document) -> IndexModel {
+    IndexModel::builder()
+        .keys(keys)
+        .options(IndexOptions::builder().unique(true).build())
+        .build()
+}
@@ -0,0 +1,35 @@
+use crate::model::auth::user::{Role, UserMode};
+use mongodb::bson::oid::ObjectId;
+use mongodb::bson::DateTime;
+use serde::{Deserialize, Serialize};
+
+/// An action of a admin on a user.
+///
+/// The logs are kept after the user is purged.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuditLog {
+    pub _id: ObjectId,
+    /// The admin who did the action
+    pub admin_id: ObjectId,
+    /// The user of the action
+    pub user_id: ObjectId,
+    pub action: AuditAction,
+    pub time: DateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum AuditAction {
+    SetRoles(Vec<Role>),
+    SetModes(Vec<UserMode>),
+    ReviewTeacher {
+        verification_id: String,
+        approved: bool,
+    },
+    ViewLoginEvents,
+    VerifyEmail,
+    ResetPassword,
+    Suspend {
+        reason: Option<String>,
+    },
+    Unsuspend,
+}
@@ -0,0 +1,29 @@
+use mongodb::bson::oid::ObjectId;
+use mongodb::bson::DateTime;
+use serde::{Deserialize, Serialize};
+
+use super::user::ConnectType;
+
+/// A login attempt of the user.
+///
+/// The document is removed at `exp`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoginEvent {
+    pub _id: ObjectId,
+    pub user_id: ObjectId,
+    pub time: DateTime,
+    pub ip: String,
+    pub user_agent: Option<String>,
+    pub method: LoginMethod,
+    pub success: bool,
+    pub exp: DateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum LoginMethod {
+    Password,
+    /// A connected account, e.g. Google or Facebook
+    Connect(ConnectType),
+    Passkey,
+    MagicLink,
+}
@@ -0,0 +1,17 @@
+use mongodb::bson::oid::ObjectId;
+use mongodb::bson::DateTime;
+use serde::{Deserialize, Serialize};
+
+/// The failed logins of a email or a ip.
+///
+/// The document is removed at `exp`, a while after the last failure.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoginFailure {
+    pub _id: ObjectId,
+    /// `email:<email>` or `ip:<ip>`
+    pub key: String,
+    pub count: i64,
+    /// The login is rejected before the time
+    pub locked_until: Option<DateTime>,
+    pub exp: DateTime,
+}
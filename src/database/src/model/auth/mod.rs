@@ -1 +1,6 @@
+pub mod login_event;
+pub mod login_failure;
+pub mod passkey_challenge;
+pub mod session;
+pub mod token;
 pub mod user;
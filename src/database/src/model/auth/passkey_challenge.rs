@@ -0,0 +1,12 @@
+use mongodb::bson::DateTime;
+use serde::{Deserialize, Serialize};
+
+/// A challenge of a passkey ceremony.
+///
+/// The challenge is removed when it is used, so a ceremony can't be replayed,
+/// or at `exp` if it is never used.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PasskeyChallenge {
+    pub challenge: String,
+    pub exp: DateTime,
+}
@@ -0,0 +1,20 @@
+use mongodb::bson::oid::ObjectId;
+use mongodb::bson::DateTime;
+use serde::{Deserialize, Serialize};
+
+/// A login session of the user, with the same id as the refresh token family.
+///
+/// The document is removed at `exp`, when the last refresh token of the session expires.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Session {
+    pub _id: ObjectId,
+    pub user_id: ObjectId,
+    /// The readable device name from the user agent, e.g. `Chrome on ChromeOS`
+    pub device_name: String,
+    /// The ip of the last request
+    pub ip: String,
+    pub user_agent: Option<String>,
+    pub created_at: DateTime,
+    pub last_seen: DateTime,
+    pub exp: DateTime,
+}
@@ -0,0 +1,36 @@
+use mongodb::bson::oid::ObjectId;
+use mongodb::bson::DateTime;
+use serde::{Deserialize, Serialize};
+
+/// A opaque refresh token, only the hash of the token is stored.
+///
+/// Every rotation creates a new token in the same `family`,
+/// the whole family is revoked when a used token is presented again.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RefreshToken {
+    pub _id: ObjectId,
+    pub user_id: ObjectId,
+    pub family: ObjectId,
+    pub token_hash: String,
+    pub used: bool,
+    pub revoked: bool,
+    /// The time (as UTC timestamp) when the user logged in
+    pub auth_time: i64,
+    pub exp: DateTime,
+}
+
+/// A revoked access token.
+///
+/// Revokes the token with `jti`, the tokens of the session `sid`,
+/// or every token of the user issued at or before `issued_before`.
+/// The document can be removed at `exp` since the revoked tokens have expired.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RevokedToken {
+    pub _id: ObjectId,
+    pub user_id: ObjectId,
+    pub jti: Option<String>,
+    #[serde(default)]
+    pub sid: Option<String>,
+    pub issued_before: Option<i64>,
+    pub exp: DateTime,
+}
@@ -1,4 +1,5 @@
 use mongodb::bson::oid::ObjectId;
+use mongodb::bson::DateTime;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -10,26 +11,101 @@ pub struct User {
     pub password_hash: Option<String>,
     pub connects: Vec<ConnectAccount>,
     pub modes: Vec<UserMode>,
+    /// The login ips recorded before the login events, they are not updated anymore
+    #[serde(default)]
     pub login_ips: Vec<String>,
+    /// The roles granted by the server, unlike the modes they can't be chosen by the user
+    #[serde(default)]
+    pub roles: Vec<Role>,
+    /// The TOTP secret of two-factor authentication, [`None`] if it is not enabled
+    #[serde(default)]
+    pub totp_secret: Option<String>,
+    /// The TOTP secret waiting for the confirmation code
+    #[serde(default)]
+    pub totp_pending_secret: Option<String>,
+    /// The bcrypt hashes of the two-factor authentication recovery codes
+    #[serde(default)]
+    pub recovery_codes: Vec<String>,
+    /// The time step of the last accepted TOTP code, a code can't be used again in its validity window
+    #[serde(default)]
+    pub totp_last_step: Option<i64>,
+    #[serde(default)]
+    pub passkeys: Vec<Passkey>,
+    /// The time when the last verification email was sent
+    #[serde(default)]
+    pub verify_email_sent_at: Option<DateTime>,
+    /// The time when the last data export was requested
+    #[serde(default)]
+    pub data_exported_at: Option<DateTime>,
+    /// The hash of the nonce of the last magic link, removed after the link is used
+    #[serde(default)]
+    pub magic_link_nonce: Option<String>,
+    /// The time when the user is purged, [`None`] if the deletion is not requested
+    #[serde(default)]
+    pub delete_at: Option<DateTime>,
+    /// The time when the user is suspended by a admin, [`None`] if the user is not suspended
+    #[serde(default)]
+    pub suspended_at: Option<DateTime>,
+    /// The reason of the suspension shown to the user
+    #[serde(default)]
+    pub suspend_reason: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub enum ConnectType {
     Google,
     Facebook,
-    // TaiwanCloudEducation,
+    TaiwanCloudEducation,
+    /// A OpenID Connect provider, with the issuer url
+    OpenIdConnect(String),
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ConnectAccount {
     pub account_type: ConnectType,
+    /// The stable user id of the provider, [`None`] for the accounts connected before it was stored
+    #[serde(default)]
+    pub subject: Option<String>,
     pub name: String,
     pub email: String,
+    /// The avatar url of the account
+    #[serde(default)]
+    pub avatar: Option<String>,
+    /// The last login time with the account
+    #[serde(default)]
+    pub last_login: Option<DateTime>,
+    /// The school id, only of the Taiwan Cloud Education account
+    #[serde(default)]
+    pub school_id: Option<String>,
+    /// The grade, only of the Taiwan Cloud Education account
+    #[serde(default)]
+    pub grade: Option<String>,
 }
 
+/// A WebAuthn credential of the user
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Passkey {
+    /// The base64url credential id
+    pub credential_id: String,
+    /// The base64url COSE public key
+    pub public_key: String,
+    pub sign_count: i64,
+    pub name: String,
+    pub created_at: DateTime,
+}
+
+/// The mode chosen by the user, only used for the interface and grants no permission
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub enum UserMode {
     Student,
     Teacher,
     Parents,
 }
+
+/// A role granted by the server, see the permissions of the roles in `util::role`
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Admin,
+    SchoolAdmin,
+    Teacher,
+}
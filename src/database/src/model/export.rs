@@ -0,0 +1,17 @@
+use mongodb::bson::oid::ObjectId;
+use mongodb::bson::DateTime;
+use serde::{Deserialize, Serialize};
+
+/// A personal data export of the user, downloaded by the token in the email.
+///
+/// Only the hash of the token is stored, the document is removed at `exp`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DataExport {
+    pub _id: ObjectId,
+    pub user_id: ObjectId,
+    pub token_hash: String,
+    /// The JSON archive of the user data
+    pub data: String,
+    pub created_at: DateTime,
+    pub exp: DateTime,
+}
@@ -0,0 +1,53 @@
+use mongodb::bson::oid::ObjectId;
+use mongodb::bson::spec::BinarySubtype;
+use mongodb::bson::{Binary, DateTime};
+use serde::{Deserialize, Serialize};
+
+/// A request of the user to be verified as a teacher, reviewed by the admins.
+///
+/// The user is granted the teacher role and mode after the request is approved.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TeacherVerification {
+    pub _id: ObjectId,
+    pub user_id: ObjectId,
+    /// The school the user teaches at
+    pub school: String,
+    /// The staff email given by the school
+    pub staff_email: Option<String>,
+    /// The staff email is confirmed by the code sent to it
+    pub staff_email_verified: bool,
+    /// The uploaded supporting document, e.g. a teacher certificate
+    pub document: Option<VerificationDocument>,
+    /// The note to the reviewers
+    pub note: Option<String>,
+    pub status: VerificationStatus,
+    pub created_at: DateTime,
+    /// The admin who reviewed the request
+    pub reviewer_id: Option<ObjectId>,
+    /// The note of the reviewer, e.g. the reason of the rejection
+    pub review_note: Option<String>,
+    pub reviewed_at: Option<DateTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VerificationDocument {
+    pub name: String,
+    pub content_type: String,
+    /// Empty if the data is excluded by the projection of the query
+    #[serde(default = "empty_data")]
+    pub data: Binary,
+}
+
+fn empty_data() -> Binary {
+    Binary {
+        subtype: BinarySubtype::Generic,
+        bytes: vec![],
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum VerificationStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
@@ -0,0 +1,614 @@
+use crate::apis::admin::audit::{page_options, record_audit_log};
+use crate::apis::authentication::util::revoke_all_sessions;
+use crate::data::admin_data::{
+    AdminUserInfo, SetModesData, SetRolesData, SuspendUserData, UserList,
+};
+use crate::data::auth_data::RESET_PASSWORD_EXP;
+use crate::data::code::Code;
+use crate::data::response::Response;
+use crate::data::role::{Admin, RequireRole, RoleError};
+use crate::data::user::LoginEventInfo;
+use crate::Config;
+use database::model::audit_log::AuditAction;
+use database::model::auth::user::{Role, User, UserMode};
+use database::mongodb::bson::{self, oid::ObjectId, DateTime, Document, Regex};
+use database::mongodb::options::{FindOneAndUpdateOptions, FindOptions, ReturnDocument};
+use database::{doc, Database};
+use rocket::fairing::AdHoc;
+use rocket::form::Form;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::State;
+use util::email::{send_reset_password_email, ResetPasswordClaims};
+use util::jwt::{create_jwt_token, TokenPurpose};
+use util::role::roles_include;
+use util::util::{create_exp, escape_regex, hash_token};
+
+/// The default number of the login events
+const DEFAULT_LOGIN_EVENT_LIMIT: i64 = 50;
+/// The maximum number of the login events
+const MAX_LOGIN_EVENT_LIMIT: i64 = 100;
+
+fn user_not_found() -> RoleError {
+    (Status::NotFound, Response::new(Code::UserNotFound, None))
+}
+
+/// Parse the user id of the path
+fn parse_user_id(id: &str) -> Result<ObjectId, RoleError> {
+    ObjectId::parse_str(id).map_err(|_| user_not_found())
+}
+
+/// Update the user, and return the updated user
+async fn update_user(
+    db: &Database,
+    user_id: ObjectId,
+    update: Document,
+) -> Result<User, RoleError> {
+    let option = FindOneAndUpdateOptions::builder()
+        .return_document(ReturnDocument::After)
+        .build();
+
+    db.user
+        .as_ref()
+        .unwrap()
+        .find_one_and_update(doc! { "_id": user_id }, update, option)
+        .await
+        .unwrap()
+        .ok_or_else(user_not_found)
+}
+
+/// # Search the users
+/// All the conditions must be matched, the oldest user is the first.
+/// ## Request
+/// - Path `/admin/users`
+/// - Method: `GET`
+/// - Parameters
+///     - `email` - Optional, a part of the email, case insensitive
+///     - `username` - Optional, a part of the username, case insensitive
+///     - `mode` - Optional, `Student`, `Teacher` or `Parents`
+///     - `verified_email` - Optional
+///     - `page` - Optional, starts from 1
+///     - `limit` - Optional, the number of the users of a page, 20 by default and 100 at most
+/// - [X] Authorization, the [Role::Admin] role is required
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::AuthError]
+///     - [Code::LoginUserNotFoundError]
+///     - [Code::PermissionDenied]
+/// - Content
+///     - [UserList]
+/// ## Curl Example
+/// ```bash
+/// curl -X GET -H "Authorization: Bearer {Token}" "http://<host>/admin/users?email=lipoic&mode=Teacher&page=1"
+/// ```
+#[allow(clippy::too_many_arguments)]
+#[get("/users?<email>&<username>&<mode>&<verified_email>&<page>&<limit>")]
+async fn search_users(
+    email: Option<&str>,
+    username: Option<&str>,
+    mode: Option<&str>,
+    verified_email: Option<bool>,
+    page: Option<u64>,
+    limit: Option<i64>,
+    admin: Result<RequireRole<Admin>, RoleError>,
+    db: &State<Database>,
+) -> Result<Json<Response<UserList>>, RoleError> {
+    admin?;
+
+    let contains = |text: &str| Regex {
+        pattern: escape_regex(text),
+        options: "i".to_string(),
+    };
+
+    let mut filter = doc! {};
+    if let Some(email) = email {
+        filter.insert("email", contains(email));
+    }
+    if let Some(username) = username {
+        filter.insert("username", contains(username));
+    }
+    if let Some(mode) = mode {
+        filter.insert("modes", mode);
+    }
+    if let Some(verified_email) = verified_email {
+        filter.insert("verified_email", verified_email);
+    }
+
+    let user = db.user.as_ref().unwrap();
+    let total = user.count_documents(filter.clone(), None).await.unwrap();
+
+    let (skip, limit) = page_options(page, limit);
+    let option = FindOptions::builder()
+        .sort(doc! { "_id": 1 })
+        .skip(skip)
+        .limit(limit)
+        .build();
+
+    let mut cursor = user.find(filter, option).await.unwrap();
+
+    let mut users = vec![];
+    while cursor.advance().await.unwrap() {
+        users.push(AdminUserInfo::from(cursor.deserialize_current().unwrap()));
+    }
+
+    Ok(Response::new(Code::Ok, Some(UserList { total, users })))
+}
+
+/// # Get a user
+/// The connected accounts are included.
+/// ## Request
+/// - Path `/admin/users/<id>`
+///     - `id` - The id of the user
+/// - Method: `GET`
+/// - [X] Authorization, the [Role::Admin] role is required
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::AuthError]
+///     - [Code::LoginUserNotFoundError]
+///     - [Code::PermissionDenied]
+///     - [Code::UserNotFound]
+/// - Content
+///     - [AdminUserInfo]
+/// ## Curl Example
+/// ```bash
+/// curl -X GET -H "Authorization: Bearer {Token}" http://<host>/admin/users/{id}
+/// ```
+#[get("/users/<id>")]
+async fn get_user(
+    id: &str,
+    admin: Result<RequireRole<Admin>, RoleError>,
+    db: &State<Database>,
+) -> Result<Json<Response<AdminUserInfo>>, RoleError> {
+    admin?;
+
+    let user = db
+        .user
+        .as_ref()
+        .unwrap()
+        .find_one(doc! { "_id": parse_user_id(id)? }, None)
+        .await
+        .unwrap()
+        .ok_or_else(user_not_found)?;
+
+    Ok(Response::new(Code::Ok, Some(AdminUserInfo::from(user))))
+}
+
+/// # Get the login history of a user
+/// The latest event is the first, the view is recorded in the audit log.
+/// ## Request
+/// - Path `/admin/users/<id>/logins`
+///     - `id` - The id of the user
+/// - Method: `GET`
+/// - Parameters
+///     - `limit` - Optional, the number of the events, 50 by default and 100 at most
+/// - [X] Authorization, the [Role::Admin] role is required
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::AuthError]
+///     - [Code::LoginUserNotFoundError]
+///     - [Code::PermissionDenied]
+///     - [Code::UserNotFound]
+/// - Content
+///     - [Vec]<[LoginEventInfo]>
+/// ## Curl Example
+/// ```bash
+/// curl -X GET -H "Authorization: Bearer {Token}" http://<host>/admin/users/{id}/logins?limit=20
+/// ```
+#[get("/users/<id>/logins?<limit>")]
+async fn get_user_login_events(
+    id: &str,
+    limit: Option<i64>,
+    admin: Result<RequireRole<Admin>, RoleError>,
+    db: &State<Database>,
+) -> Result<Json<Response<Vec<LoginEventInfo>>>, RoleError> {
+    let admin = admin?;
+    let user_id = parse_user_id(id)?;
+
+    if db
+        .user
+        .as_ref()
+        .unwrap()
+        .find_one(doc! { "_id": user_id }, None)
+        .await
+        .unwrap()
+        .is_none()
+    {
+        return Err(user_not_found());
+    }
+
+    let limit = limit
+        .unwrap_or(DEFAULT_LOGIN_EVENT_LIMIT)
+        .clamp(1, MAX_LOGIN_EVENT_LIMIT);
+    let option = FindOptions::builder()
+        .sort(doc! { "time": -1 })
+        .limit(limit)
+        .build();
+
+    let mut cursor = db
+        .login_events
+        .as_ref()
+        .unwrap()
+        .find(doc! { "user_id": user_id }, option)
+        .await
+        .unwrap();
+
+    let mut login_events = vec![];
+    while cursor.advance().await.unwrap() {
+        login_events.push(LoginEventInfo::from(cursor.deserialize_current().unwrap()));
+    }
+
+    record_audit_log(db, admin.user._id, user_id, AuditAction::ViewLoginEvents)
+        .await
+        .unwrap();
+
+    Ok(Response::new(Code::Ok, Some(login_events)))
+}
+
+/// # Verify the email of a user
+/// ## Request
+/// - Path `/admin/users/<id>/verify-email`
+///     - `id` - The id of the user
+/// - Method: `POST`
+/// - [X] Authorization, the [Role::Admin] role is required
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::AuthError]
+///     - [Code::LoginUserNotFoundError]
+///     - [Code::PermissionDenied]
+///     - [Code::UserNotFound]
+/// - Content
+///     - [AdminUserInfo]
+/// ## Curl Example
+/// ```bash
+/// curl -X POST -H "Authorization: Bearer {Token}" http://<host>/admin/users/{id}/verify-email
+/// ```
+#[post("/users/<id>/verify-email")]
+async fn verify_user_email(
+    id: &str,
+    admin: Result<RequireRole<Admin>, RoleError>,
+    db: &State<Database>,
+) -> Result<Json<Response<AdminUserInfo>>, RoleError> {
+    let admin = admin?;
+
+    let user = update_user(
+        db,
+        parse_user_id(id)?,
+        doc! {
+            "$set": { "verified_email": true },
+            "$unset": { "verify_email_sent_at": "" }
+        },
+    )
+    .await?;
+
+    record_audit_log(db, admin.user._id, user._id, AuditAction::VerifyEmail)
+        .await
+        .unwrap();
+
+    Ok(Response::new(Code::Ok, Some(AdminUserInfo::from(user))))
+}
+
+/// # Reset the password of a user
+/// The password is removed and all the sessions are logged out,
+/// a reset password link is sent to the email of the user.
+/// ## Request
+/// - Path `/admin/users/<id>/reset-password`
+///     - `id` - The id of the user
+/// - Method: `POST`
+/// - [X] Authorization, the [Role::Admin] role is required
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::AuthError]
+///     - [Code::LoginUserNotFoundError]
+///     - [Code::PermissionDenied]
+///     - [Code::UserNotFound]
+/// - Content
+///     - [AdminUserInfo]
+/// ## Curl Example
+/// ```bash
+/// curl -X POST -H "Authorization: Bearer {Token}" http://<host>/admin/users/{id}/reset-password
+/// ```
+#[post("/users/<id>/reset-password")]
+async fn reset_user_password(
+    id: &str,
+    admin: Result<RequireRole<Admin>, RoleError>,
+    db: &State<Database>,
+    config: &State<Config>,
+) -> Result<Json<Response<AdminUserInfo>>, RoleError> {
+    let admin = admin?;
+
+    let user = update_user(
+        db,
+        parse_user_id(id)?,
+        doc! { "$unset": { "password_hash": "" } },
+    )
+    .await?;
+
+    revoke_all_sessions(db, user._id).await.unwrap();
+
+    // The reset password code of the removed password.
+    let code = create_jwt_token(
+        config.private_key.as_bytes(),
+        ResetPasswordClaims {
+            exp: create_exp(RESET_PASSWORD_EXP),
+            purpose: TokenPurpose::ResetPassword,
+            email: user.email.clone(),
+            password: hash_token(""),
+        },
+    )
+    .unwrap();
+
+    send_reset_password_email(
+        &config.google_account_email,
+        &config.google_account_password,
+        &config.reset_password_url,
+        code,
+        &user.email,
+    );
+
+    record_audit_log(db, admin.user._id, user._id, AuditAction::ResetPassword)
+        .await
+        .unwrap();
+
+    Ok(Response::new(Code::Ok, Some(AdminUserInfo::from(user))))
+}
+
+/// # Suspend a user
+/// The user can't login and all the sessions are logged out, the admin can't suspend itself.
+/// ## Request
+/// - Path `/admin/users/<id>/suspend`
+///     - `id` - The id of the user
+/// - Method: `POST`
+/// - FromData [SuspendUserData]
+/// - [X] Authorization, the [Role::Admin] role is required
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::AuthError]
+///     - [Code::LoginUserNotFoundError]
+///     - [Code::PermissionDenied]
+///     - [Code::UserNotFound]
+/// - Content
+///     - [AdminUserInfo]
+/// ## Curl Example
+/// ```bash
+/// curl -X POST -H "Authorization: Bearer {Token}" -F reason=spam http://<host>/admin/users/{id}/suspend
+/// ```
+#[post("/users/<id>/suspend", data = "<suspend_user_data>")]
+async fn suspend_user(
+    id: &str,
+    suspend_user_data: Form<SuspendUserData>,
+    admin: Result<RequireRole<Admin>, RoleError>,
+    db: &State<Database>,
+) -> Result<Json<Response<AdminUserInfo>>, RoleError> {
+    let admin = admin?;
+    let user_id = parse_user_id(id)?;
+
+    if user_id == admin.user._id {
+        return Err((
+            Status::Forbidden,
+            Response::new(Code::PermissionDenied, None),
+        ));
+    }
+
+    let user = update_user(
+        db,
+        user_id,
+        doc! {
+            "$set": {
+                "suspended_at": DateTime::now(),
+                "suspend_reason": &suspend_user_data.reason
+            }
+        },
+    )
+    .await?;
+
+    revoke_all_sessions(db, user._id).await.unwrap();
+
+    record_audit_log(
+        db,
+        admin.user._id,
+        user._id,
+        AuditAction::Suspend {
+            reason: suspend_user_data.reason.clone(),
+        },
+    )
+    .await
+    .unwrap();
+
+    Ok(Response::new(Code::Ok, Some(AdminUserInfo::from(user))))
+}
+
+/// # Unsuspend a user
+/// ## Request
+/// - Path `/admin/users/<id>/unsuspend`
+///     - `id` - The id of the user
+/// - Method: `POST`
+/// - [X] Authorization, the [Role::Admin] role is required
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::AuthError]
+///     - [Code::LoginUserNotFoundError]
+///     - [Code::PermissionDenied]
+///     - [Code::UserNotFound]
+/// - Content
+///     - [AdminUserInfo]
+/// ## Curl Example
+/// ```bash
+/// curl -X POST -H "Authorization: Bearer {Token}" http://<host>/admin/users/{id}/unsuspend
+/// ```
+#[post("/users/<id>/unsuspend")]
+async fn unsuspend_user(
+    id: &str,
+    admin: Result<RequireRole<Admin>, RoleError>,
+    db: &State<Database>,
+) -> Result<Json<Response<AdminUserInfo>>, RoleError> {
+    let admin = admin?;
+
+    let user = update_user(
+        db,
+        parse_user_id(id)?,
+        doc! { "$unset": { "suspended_at": "", "suspend_reason": "" } },
+    )
+    .await?;
+
+    record_audit_log(db, admin.user._id, user._id, AuditAction::Unsuspend)
+        .await
+        .unwrap();
+
+    Ok(Response::new(Code::Ok, Some(AdminUserInfo::from(user))))
+}
+
+/// # Set the modes of a user
+/// The modes are replaced, the teacher mode requires the [Role::Teacher] role.
+/// ## Request
+/// - Path `/admin/users/<id>/modes`
+///     - `id` - The id of the user
+/// - Method: `PUT`
+/// - FromData [SetModesData]
+/// - [X] Authorization, the [Role::Admin] role is required
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::AuthError]
+///     - [Code::LoginUserNotFoundError]
+///     - [Code::PermissionDenied]
+///     - [Code::UserNotFound]
+///     - [Code::TeacherVerificationRequired]
+/// - Content
+///     - [AdminUserInfo]
+/// ## Curl Example
+/// ```bash
+/// curl -X PUT -H "Authorization: Bearer {Token}" -F modes='["Student","Parents"]' http://<host>/admin/users/{id}/modes
+/// ```
+#[put("/users/<id>/modes", data = "<set_modes_data>")]
+async fn set_modes(
+    id: &str,
+    set_modes_data: Form<SetModesData>,
+    admin: Result<RequireRole<Admin>, RoleError>,
+    db: &State<Database>,
+) -> Result<Json<Response<AdminUserInfo>>, RoleError> {
+    let admin = admin?;
+
+    let mut modes: Vec<UserMode> = vec![];
+    for mode in set_modes_data.modes.iter() {
+        if !modes.contains(mode) {
+            modes.push(mode.clone());
+        }
+    }
+
+    let user_id = parse_user_id(id)?;
+    let user = db
+        .user
+        .as_ref()
+        .unwrap()
+        .find_one(doc! { "_id": user_id }, None)
+        .await
+        .unwrap()
+        .ok_or_else(user_not_found)?;
+
+    if modes.contains(&UserMode::Teacher) && !roles_include(&user.roles, Role::Teacher) {
+        return Err((
+            Status::Forbidden,
+            Response::new(Code::TeacherVerificationRequired, None),
+        ));
+    }
+
+    let user = update_user(
+        db,
+        user_id,
+        doc! { "$set": { "modes": bson::to_bson(&modes).unwrap() } },
+    )
+    .await?;
+
+    record_audit_log(db, admin.user._id, user._id, AuditAction::SetModes(modes))
+        .await
+        .unwrap();
+
+    Ok(Response::new(Code::Ok, Some(AdminUserInfo::from(user))))
+}
+
+/// # Set the roles of a user
+/// The roles are replaced, the admin can't remove the [Role::Admin] role of itself.
+/// ## Request
+/// - Path `/admin/users/<id>/roles`
+///     - `id` - The id of the user
+/// - Method: `PUT`
+/// - FromData [SetRolesData]
+/// - [X] Authorization, the [Role::Admin] role is required
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::AuthError]
+///     - [Code::LoginUserNotFoundError]
+///     - [Code::PermissionDenied]
+///     - [Code::UserNotFound]
+/// - Content
+///     - [AdminUserInfo]
+/// ## Curl Example
+/// ```bash
+/// curl -X PUT -H "Authorization: Bearer {Token}" -F roles='["Teacher"]' http://<host>/admin/users/{id}/roles
+/// ```
+#[put("/users/<id>/roles", data = "<set_roles_data>")]
+async fn set_roles(
+    id: &str,
+    set_roles_data: Form<SetRolesData>,
+    admin: Result<RequireRole<Admin>, RoleError>,
+    db: &State<Database>,
+) -> Result<Json<Response<AdminUserInfo>>, RoleError> {
+    let admin = admin?;
+    let user_id = parse_user_id(id)?;
+
+    let mut roles: Vec<Role> = vec![];
+    for role in set_roles_data.roles.iter() {
+        if !roles.contains(role) {
+            roles.push(*role);
+        }
+    }
+
+    // Avoid the last admin locking itself out.
+    if user_id == admin.user._id && !roles.contains(&Role::Admin) {
+        return Err((
+            Status::Forbidden,
+            Response::new(Code::PermissionDenied, None),
+        ));
+    }
+
+    let user = update_user(
+        db,
+        user_id,
+        doc! { "$set": { "roles": bson::to_bson(&roles).unwrap() } },
+    )
+    .await?;
+
+    record_audit_log(db, admin.user._id, user._id, AuditAction::SetRoles(roles))
+        .await
+        .unwrap();
+
+    Ok(Response::new(Code::Ok, Some(AdminUserInfo::from(user))))
+}
+
+#[doc(hidden)]
+pub fn stage() -> AdHoc {
+    AdHoc::on_ignite("load admin stage", |rocket| async {
+        rocket.mount(
+            "/admin",
+            routes![
+                search_users,
+                get_user,
+                get_user_login_events,
+                verify_user_email,
+                reset_user_password,
+                suspend_user,
+                unsuspend_user,
+                set_modes,
+                set_roles
+            ],
+        )
+    })
+}
@@ -0,0 +1,120 @@
+use crate::data::admin_data::AuditLogInfo;
+use crate::data::code::Code;
+use crate::data::response::Response;
+use crate::data::role::{Admin, RequireRole, RoleError};
+use database::model::audit_log::{AuditAction, AuditLog};
+use database::mongodb::bson::{oid::ObjectId, DateTime};
+use database::mongodb::options::FindOptions;
+use database::{doc, Database, Error};
+use rocket::fairing::AdHoc;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::State;
+
+/// The default number of the items of a page
+pub const DEFAULT_PAGE_LIMIT: i64 = 20;
+/// The maximum number of the items of a page
+pub const MAX_PAGE_LIMIT: i64 = 100;
+
+/// Record the action of the admin on the user
+pub async fn record_audit_log(
+    db: &Database,
+    admin_id: ObjectId,
+    user_id: ObjectId,
+    action: AuditAction,
+) -> Result<(), Error> {
+    info!("Admin {} on user {}: {:?}", admin_id, user_id, action);
+
+    db.audit_log
+        .as_ref()
+        .unwrap()
+        .insert_one(
+            AuditLog {
+                _id: ObjectId::new(),
+                admin_id,
+                user_id,
+                action,
+                time: DateTime::now(),
+            },
+            None,
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// The skip and the limit of the page of the admin APIs, the page starts from 1
+pub fn page_options(page: Option<u64>, limit: Option<i64>) -> (u64, i64) {
+    util::util::page_options(page, limit, DEFAULT_PAGE_LIMIT, MAX_PAGE_LIMIT)
+}
+
+/// # Get the audit logs
+/// The latest log is the first.
+/// ## Request
+/// - Path `/admin/audit-logs`
+/// - Method: `GET`
+/// - Parameters
+///     - `user_id` - Optional, only the logs of the user
+///     - `page` - Optional, starts from 1
+///     - `limit` - Optional, the number of the logs of a page, 20 by default and 100 at most
+/// - [X] Authorization, the [Role::Admin](database::model::auth::user::Role::Admin) role is required
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::AuthError]
+///     - [Code::LoginUserNotFoundError]
+///     - [Code::PermissionDenied]
+///     - [Code::UserNotFound]
+/// - Content
+///     - [Vec]<[AuditLogInfo]>
+/// ## Curl Example
+/// ```bash
+/// curl -X GET -H "Authorization: Bearer {Token}" http://<host>/admin/audit-logs?page=1&limit=50
+/// ```
+#[get("/audit-logs?<user_id>&<page>&<limit>")]
+async fn get_audit_logs(
+    user_id: Option<&str>,
+    page: Option<u64>,
+    limit: Option<i64>,
+    admin: Result<RequireRole<Admin>, RoleError>,
+    db: &State<Database>,
+) -> Result<Json<Response<Vec<AuditLogInfo>>>, RoleError> {
+    admin?;
+
+    let filter = match user_id {
+        Some(user_id) => doc! {
+            "user_id": ObjectId::parse_str(user_id)
+                .map_err(|_| (Status::NotFound, Response::new(Code::UserNotFound, None)))?
+        },
+        None => doc! {},
+    };
+
+    let (skip, limit) = page_options(page, limit);
+    let option = FindOptions::builder()
+        .sort(doc! { "time": -1 })
+        .skip(skip)
+        .limit(limit)
+        .build();
+
+    let mut cursor = db
+        .audit_log
+        .as_ref()
+        .unwrap()
+        .find(filter, option)
+        .await
+        .unwrap();
+
+    let mut audit_logs = vec![];
+    while cursor.advance().await.unwrap() {
+        audit_logs.push(AuditLogInfo::from(cursor.deserialize_current().unwrap()));
+    }
+
+    Ok(Response::new(Code::Ok, Some(audit_logs)))
+}
+
+#[doc(hidden)]
+pub fn stage() -> AdHoc {
+    AdHoc::on_ignite("load audit stage", |rocket| async {
+        rocket.mount("/admin", routes![get_audit_logs])
+    })
+}
@@ -0,0 +1,3 @@
+pub mod api;
+pub mod audit;
+pub mod teacher;
@@ -0,0 +1,266 @@
+use crate::apis::admin::audit::record_audit_log;
+use crate::data::admin_data::ReviewTeacherData;
+use crate::data::code::Code;
+use crate::data::response::Response;
+use crate::data::role::{RequireRole, RoleError, SchoolAdmin};
+use crate::data::user::TeacherVerificationInfo;
+use database::model::audit_log::AuditAction;
+use database::model::auth::user::{Role, UserMode};
+use database::model::teacher_verification::VerificationStatus;
+use database::mongodb::bson::{self, oid::ObjectId, DateTime};
+use database::mongodb::options::{FindOneAndUpdateOptions, FindOptions, ReturnDocument};
+use database::{doc, Database};
+use rocket::fairing::AdHoc;
+use rocket::form::Form;
+use rocket::http::{ContentType, Header, Status};
+use rocket::serde::json::Json;
+use rocket::State;
+
+/// The maximum number of the listed requests
+const MAX_TEACHER_VERIFICATION_LIMIT: i64 = 100;
+
+/// The uploaded document of a teacher verification request
+#[derive(Responder)]
+pub struct DocumentFile(Vec<u8>, ContentType, Header<'static>);
+
+/// # List the teacher verification requests
+/// The oldest request is the first, at most 100 requests are responded.
+/// ## Request
+/// - Path `/admin/teacher-verifications`
+/// - Method: `GET`
+/// - Parameters
+///     - `status` - Optional, `Pending` (default), `Approved` or `Rejected`
+/// - [X] Authorization, the [Role::SchoolAdmin] role is required
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::AuthError]
+///     - [Code::LoginUserNotFoundError]
+///     - [Code::PermissionDenied]
+/// - Content
+///     - [Vec]<[TeacherVerificationInfo]>
+/// ## Curl Example
+/// ```bash
+/// curl -X GET -H "Authorization: Bearer {Token}" http://<host>/admin/teacher-verifications?status=Pending
+/// ```
+#[get("/teacher-verifications?<status>")]
+async fn get_teacher_verifications(
+    status: Option<&str>,
+    reviewer: Result<RequireRole<SchoolAdmin>, RoleError>,
+    db: &State<Database>,
+) -> Result<Json<Response<Vec<TeacherVerificationInfo>>>, RoleError> {
+    reviewer?;
+
+    let status = match status.unwrap_or("Pending") {
+        "Approved" => VerificationStatus::Approved,
+        "Rejected" => VerificationStatus::Rejected,
+        _ => VerificationStatus::Pending,
+    };
+
+    let option = FindOptions::builder()
+        .sort(doc! { "created_at": 1 })
+        .limit(MAX_TEACHER_VERIFICATION_LIMIT)
+        .projection(doc! { "document.data": 0 })
+        .build();
+
+    let mut cursor = db
+        .teacher_verification
+        .as_ref()
+        .unwrap()
+        .find(doc! { "status": bson::to_bson(&status).unwrap() }, option)
+        .await
+        .unwrap();
+
+    let mut verifications = vec![];
+    while cursor.advance().await.unwrap() {
+        verifications.push(TeacherVerificationInfo::from(
+            cursor.deserialize_current().unwrap(),
+        ));
+    }
+
+    Ok(Response::new(Code::Ok, Some(verifications)))
+}
+
+/// # Download the document of a teacher verification request
+/// ## Request
+/// - Path `/admin/teacher-verifications/<id>/document`
+///     - `id` - The id of the request
+/// - Method: `GET`
+/// - [X] Authorization, the [Role::SchoolAdmin] role is required
+/// ## Response
+/// - Code
+///     - [Code::AuthError]
+///     - [Code::LoginUserNotFoundError]
+///     - [Code::PermissionDenied]
+///     - [Code::TeacherVerificationNotFound]
+/// - Content
+///     - The uploaded file
+/// ## Curl Example
+/// ```bash
+/// curl -X GET -H "Authorization: Bearer {Token}" -o document http://<host>/admin/teacher-verifications/{id}/document
+/// ```
+#[get("/teacher-verifications/<id>/document")]
+async fn download_teacher_document(
+    id: &str,
+    reviewer: Result<RequireRole<SchoolAdmin>, RoleError>,
+    db: &State<Database>,
+) -> Result<DocumentFile, RoleError> {
+    reviewer?;
+
+    let not_found = || {
+        (
+            Status::NotFound,
+            Response::new(Code::TeacherVerificationNotFound, None),
+        )
+    };
+
+    let document = db
+        .teacher_verification
+        .as_ref()
+        .unwrap()
+        .find_one(
+            doc! { "_id": ObjectId::parse_str(id).map_err(|_| not_found())? },
+            None,
+        )
+        .await
+        .unwrap()
+        .and_then(|verification| verification.document)
+        .ok_or_else(not_found)?;
+
+    let content_type =
+        ContentType::parse_flexible(&document.content_type).unwrap_or(ContentType::Binary);
+    // Always downloaded, the uploaded file is not trusted.
+    let file_name = document.name.replace('"', "");
+
+    Ok(DocumentFile(
+        document.data.bytes,
+        content_type,
+        Header::new(
+            "Content-Disposition",
+            format!("attachment; filename=\"{}\"", file_name),
+        ),
+    ))
+}
+
+/// # Review a teacher verification request
+/// The user is granted the [Role::Teacher] role and the teacher mode if it is approved,
+/// the mode is in the access token after the token is refreshed.
+/// ## Request
+/// - Path `/admin/teacher-verifications/<id>/review`
+///     - `id` - The id of the pending request
+/// - Method: `POST`
+/// - FromData [ReviewTeacherData]
+/// - [X] Authorization, the [Role::SchoolAdmin] role is required
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::AuthError]
+///     - [Code::LoginUserNotFoundError]
+///     - [Code::PermissionDenied]
+///     - [Code::TeacherVerificationNotFound]
+/// - Content
+///     - [TeacherVerificationInfo]
+/// ## Curl Example
+/// ```bash
+/// curl -X POST -H "Authorization: Bearer {Token}" -F approved=true http://<host>/admin/teacher-verifications/{id}/review
+/// ```
+#[post("/teacher-verifications/<id>/review", data = "<review_teacher_data>")]
+async fn review_teacher_verification(
+    id: &str,
+    review_teacher_data: Form<ReviewTeacherData>,
+    reviewer: Result<RequireRole<SchoolAdmin>, RoleError>,
+    db: &State<Database>,
+) -> Result<Json<Response<TeacherVerificationInfo>>, RoleError> {
+    let reviewer = reviewer?;
+
+    let not_found = || {
+        (
+            Status::NotFound,
+            Response::new(Code::TeacherVerificationNotFound, None),
+        )
+    };
+
+    let status = if review_teacher_data.approved {
+        VerificationStatus::Approved
+    } else {
+        VerificationStatus::Rejected
+    };
+
+    let option = FindOneAndUpdateOptions::builder()
+        .return_document(ReturnDocument::After)
+        .projection(doc! { "document.data": 0 })
+        .build();
+
+    // Only the pending request can be reviewed, a request is never reviewed twice.
+    let verification = db
+        .teacher_verification
+        .as_ref()
+        .unwrap()
+        .find_one_and_update(
+            doc! {
+                "_id": ObjectId::parse_str(id).map_err(|_| not_found())?,
+                "status": bson::to_bson(&VerificationStatus::Pending).unwrap()
+            },
+            doc! {
+                "$set": {
+                    "status": bson::to_bson(&status).unwrap(),
+                    "reviewer_id": reviewer.user._id,
+                    "review_note": &review_teacher_data.note,
+                    "reviewed_at": DateTime::now()
+                }
+            },
+            option,
+        )
+        .await
+        .unwrap()
+        .ok_or_else(not_found)?;
+
+    if status == VerificationStatus::Approved {
+        db.user
+            .as_ref()
+            .unwrap()
+            .update_one(
+                doc! { "_id": verification.user_id },
+                doc! {
+                    "$addToSet": {
+                        "roles": bson::to_bson(&Role::Teacher).unwrap(),
+                        "modes": bson::to_bson(&UserMode::Teacher).unwrap()
+                    }
+                },
+                None,
+            )
+            .await
+            .unwrap();
+    }
+
+    record_audit_log(
+        db,
+        reviewer.user._id,
+        verification.user_id,
+        AuditAction::ReviewTeacher {
+            verification_id: verification._id.to_hex(),
+            approved: review_teacher_data.approved,
+        },
+    )
+    .await
+    .unwrap();
+
+    Ok(Response::new(
+        Code::Ok,
+        Some(TeacherVerificationInfo::from(verification)),
+    ))
+}
+
+#[doc(hidden)]
+pub fn stage() -> AdHoc {
+    AdHoc::on_ignite("load admin teacher stage", |rocket| async {
+        rocket.mount(
+            "/admin",
+            routes![
+                get_teacher_verifications,
+                download_teacher_document,
+                review_teacher_verification
+            ],
+        )
+    })
+}
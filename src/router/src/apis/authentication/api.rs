@@ -1,158 +1,118 @@
-use database::model::auth::user::ConnectType;
-
-use database::doc;
 use database::Database;
 use rocket::fairing::AdHoc;
 
-use rocket::response::status::BadRequest;
+use rocket::http::Status;
 use rocket::serde::json::Json;
 use rocket::State;
-use util::oauth::OAuthData;
 
-use crate::data::auth_data::{AuthUrl, Token};
+use crate::data::auth_data::{AuthUrl, LoginToken};
 use crate::data::code::Code;
 use crate::data::response::Response;
 use crate::Config;
 
-use super::data::RequestIp;
-use super::util::connect_account;
+use super::data::{OAuthProviders, RequestClient};
+use super::util::{connect_account, create_oauth_url, verify_oauth_state};
 
-/// # Get Google OAuth url
-/// ## Request
-/// - Path `/authentication/google/url`
-/// - Method `GET`
-/// - Parameters
-///     - `redirect_uri`
-/// ## Response
-/// - Code
-///     - [Code::Ok]
-/// - Content
-///     - [AuthUrl] - A OAuth url
-#[get("/google/url?<redirect_uri>")]
-fn google_oauth(redirect_uri: String, config: &State<Config>) -> Json<Response<AuthUrl>> {
-    let google_auth = OAuthData {
-        account_type: ConnectType::Google,
-        client_secret: config.google_oauth_secret.clone(),
-        client_id: config.google_oauth_id.clone(),
-        redirect_uri,
-    };
+type OAuthError = (Status, Json<Response<String>>);
 
-    Response::new(
-        Code::Ok,
-        Some(AuthUrl {
-            url: google_auth.get_auth_url(),
-        }),
-    )
+fn provider_not_found() -> OAuthError {
+    (Status::NotFound, Response::new(Code::NotFound, None))
 }
 
-/// # Google OAuth2 login
+/// # Get OAuth url
 /// ## Request
-/// - Path `/authentication/google`
+/// - Path `/authentication/<provider>/url`
+///     - `provider` - `google`, `facebook`, `tce` (Taiwan Cloud Education) or a OpenID Connect provider in the config
 /// - Method `GET`
 /// - Parameters
-///     - `code` - A OAuth2 code
-///     - `oauth_redirect_uri` - A OAuth2 redirect uri
+///     - `redirect_uri`
 /// ## Response
-/// - Response Code
+/// - Code
 ///     - [Code::Ok]
-///     - [Code::OAuthCodeError]
-/// - Response Content
-///     - [Token] - A login token.
+///     - [Code::NotFound] - The provider is not found.
+/// - Content
+///     - [AuthUrl] - A OAuth url with the `state` and the PKCE code challenge, the `state` expires in 10 minutes
 /// ## Curl Example
 /// ```bash
-/// curl -X GET http://127.0.0.1:8000/authentication/google?code={code}&oauth_redirect_uri={oauth_redirect_uri}
+/// curl -X GET http://127.0.0.1:8000/authentication/google/url?redirect_uri={redirect_uri}
 /// ```
-#[get("/google?<code>&<oauth_redirect_uri>")]
-async fn google_oauth_code(
-    code: String,
-    oauth_redirect_uri: String,
+#[get("/<provider>/url?<redirect_uri>")]
+fn oauth_url(
+    provider: &str,
+    redirect_uri: String,
     config: &State<Config>,
-    db: &State<Database>,
-    request_ip: RequestIp,
-) -> Result<Json<Response<Token>>, BadRequest<Json<Response<String>>>> {
-    let google_auth = OAuthData {
-        account_type: ConnectType::Google,
-        client_secret: config.google_oauth_secret.clone(),
-        client_id: config.google_oauth_id.clone(),
-        redirect_uri: oauth_redirect_uri,
-    };
+    oauth_providers: &State<OAuthProviders>,
+) -> Result<Json<Response<AuthUrl>>, OAuthError> {
+    let oauth = oauth_providers
+        .get(provider, redirect_uri)
+        .ok_or_else(provider_not_found)?;
 
-    connect_account(
-        google_auth,
-        code,
-        db,
-        config.private_key.clone(),
-        request_ip,
-    )
-    .await
-}
-
-/// # Get Facebook OAuth url
-/// ## Request
-/// - Path `/authentication/facebook/url`
-/// - Method `GET`
-/// - Parameters
-///     - `redirect_uri`
-/// ## Response
-/// - Code
-///     - [Code::Ok]
-/// - Content
-///     - [AuthUrl] - A OAuth url
-#[get("/facebook/url?<redirect_uri>")]
-fn facebook_oauth(redirect_uri: String, config: &State<Config>) -> Json<Response<AuthUrl>> {
-    let facebook_auth = OAuthData {
-        account_type: ConnectType::Facebook,
-        client_secret: config.facebook_oauth_secret.clone(),
-        client_id: config.facebook_oauth_id.clone(),
-        redirect_uri,
-    };
-
-    Response::new(
+    Ok(Response::new(
         Code::Ok,
         Some(AuthUrl {
-            url: facebook_auth.get_auth_url(),
+            url: create_oauth_url(&oauth, &config.private_key, None),
         }),
-    )
+    ))
 }
 
-/// # Facebook OAuth2 login
+/// # OAuth2 login
 /// ## Request
-/// - Path `/authentication/facebook`
+/// - Path `/authentication/<provider>`
+///     - `provider` - `google`, `facebook`, `tce` (Taiwan Cloud Education) or a OpenID Connect provider in the config
 /// - Method `GET`
 /// - Parameters
 ///     - `code` - A OAuth2 code
-///     - `oauth_redirect_uri` - A OAuth2 redirect uri
+///     - `state` - The OAuth2 state from the OAuth url
 /// ## Response
 /// - Response Code
 ///     - [Code::Ok]
+///     - [Code::NotFound] - The provider is not found.
+///     - [Code::OAuthStateError]
 ///     - [Code::OAuthCodeError]
+///     - [Code::OAuthGetUserInfoError]
+///     - [Code::MfaRequired] - Two-factor authentication is required, see `/user/login/mfa`.
+///     - [Code::AccountPendingDeletion]
+///     - [Code::AccountSuspended]
+///     - [Code::LinkRequired] - The email of the account isn't verified by the provider
+///       and is used by a existing user, see `/user/connects/confirm`.
 /// - Response Content
-///     - [Token] - A login token.
+///     - [Token](crate::data::auth_data::Token) - A login token.
+///     - [MfaToken](crate::data::auth_data::MfaToken) - If the code is [Code::MfaRequired].
+///     - [LinkToken](crate::data::auth_data::LinkToken) - If the code is [Code::LinkRequired].
 /// ## Curl Example
 /// ```bash
-/// curl -X GET http://127.0.0.1:8000/authentication/facebook?code={code}&oauth_redirect_uri={oauth_redirect_uri}
+/// curl -X GET http://127.0.0.1:8000/authentication/google?code={code}&state={state}
 /// ```
-#[get("/facebook?<code>&<oauth_redirect_uri>")]
-async fn facebook_oauth_code(
+#[get("/<provider>?<code>&<state>")]
+async fn oauth_code(
+    provider: &str,
     code: String,
-    oauth_redirect_uri: String,
+    state: String,
     config: &State<Config>,
+    oauth_providers: &State<OAuthProviders>,
     db: &State<Database>,
-    request_ip: RequestIp,
-) -> Result<Json<Response<Token>>, BadRequest<Json<Response<String>>>> {
-    let facebook_auth = OAuthData {
-        account_type: ConnectType::Facebook,
-        client_secret: config.facebook_oauth_secret.clone(),
-        client_id: config.facebook_oauth_id.clone(),
-        redirect_uri: oauth_redirect_uri,
-    };
+    request_client: RequestClient,
+) -> Result<Json<Response<LoginToken>>, OAuthError> {
+    let mut oauth = oauth_providers
+        .get(provider, String::new())
+        .ok_or_else(provider_not_found)?;
+
+    let state_claims = verify_oauth_state(
+        &state,
+        oauth.provider.account_type(),
+        None,
+        &config.public_key,
+    )?;
+    // Use the same redirect uri as the OAuth url
+    oauth.redirect_uri = state_claims.redirect_uri;
 
     connect_account(
-        facebook_auth,
+        oauth,
         code,
+        state,
         db,
         config.private_key.clone(),
-        request_ip,
+        request_client,
     )
     .await
 }
@@ -160,14 +120,10 @@ async fn facebook_oauth_code(
 #[doc(hidden)]
 pub fn stage() -> AdHoc {
     AdHoc::on_ignite("load authentication stage", |rocket| async {
-        rocket.mount(
-            "/authentication",
-            routes![
-                google_oauth,
-                google_oauth_code,
-                facebook_oauth,
-                facebook_oauth_code
-            ],
-        )
+        let config: Config = rocket.figment().extract().expect("config");
+
+        rocket
+            .manage(OAuthProviders::new(&config.oauth).await)
+            .mount("/authentication", routes![oauth_url, oauth_code])
     })
 }
@@ -2,25 +2,103 @@ use rocket::{
     request::{FromRequest, Outcome},
     Request,
 };
+use std::collections::HashMap;
+use util::oauth::{providers, OAuthData, OAuthProvider, OpenIdConnect};
+
+use crate::OAuthConfig;
 
 #[doc(hidden)]
 pub struct CreateUserInfo {
     pub username: String,
     pub email: String,
     pub verified_email: bool,
-    pub ip: String,
 }
 
-/// Request Client IP Address
+/// Request Client IP Address and User Agent
 #[doc(hidden)]
-pub struct RequestIp(pub String);
+pub struct RequestClient {
+    pub ip: String,
+    pub user_agent: Option<String>,
+}
 
 #[rocket::async_trait]
 #[doc(hidden)]
-impl<'r> FromRequest<'r> for RequestIp {
+impl<'r> FromRequest<'r> for RequestClient {
     type Error = ();
 
     async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
-        Outcome::Success(RequestIp(request.client_ip().unwrap().to_string()))
+        Outcome::Success(RequestClient {
+            ip: request
+                .client_ip()
+                .map(|ip| ip.to_string())
+                .unwrap_or_default(),
+            user_agent: request.headers().get_one("User-Agent").map(String::from),
+        })
+    }
+}
+
+/// The client of a OAuth provider
+#[doc(hidden)]
+pub struct OAuthClient {
+    pub provider: Box<dyn OAuthProvider>,
+    pub client_id: String,
+    pub client_secret: String,
+}
+
+/// The OAuth providers which have a client in the config
+#[doc(hidden)]
+pub struct OAuthProviders(HashMap<String, OAuthClient>);
+
+impl OAuthProviders {
+    pub async fn new(config: &HashMap<String, OAuthConfig>) -> Self {
+        let mut built_in_providers = providers();
+        let mut clients = HashMap::new();
+
+        for (name, client) in config {
+            let provider: Box<dyn OAuthProvider> = match built_in_providers
+                .iter()
+                .position(|provider| provider.name() == name)
+            {
+                Some(index) => built_in_providers.swap_remove(index),
+                None => {
+                    let issuer = match &client.issuer {
+                        Some(issuer) => issuer,
+                        None => {
+                            warn!("OAuth provider `{}` needs a OpenID Connect issuer.", name);
+                            continue;
+                        }
+                    };
+
+                    match OpenIdConnect::discover(name, issuer, &client.client_id).await {
+                        Ok(provider) => Box::new(provider),
+                        Err(error) => {
+                            warn!("OpenID Connect discovery of `{}` failed: {}", name, error);
+                            continue;
+                        }
+                    }
+                }
+            };
+
+            clients.insert(
+                name.clone(),
+                OAuthClient {
+                    provider,
+                    client_id: client.client_id.clone(),
+                    client_secret: client.client_secret.clone(),
+                },
+            );
+        }
+
+        OAuthProviders(clients)
+    }
+
+    /// Get the [OAuthData] of the provider, [None] if the provider is not found
+    pub fn get(&self, name: &str, redirect_uri: String) -> Option<OAuthData<'_>> {
+        self.0.get(name).map(|client| OAuthData {
+            provider: client.provider.as_ref(),
+            client_secret: client.client_secret.clone(),
+            client_id: client.client_id.clone(),
+            redirect_uri,
+        })
     }
 }
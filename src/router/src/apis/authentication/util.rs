@@ -1,149 +1,754 @@
 use database::{
     doc,
-    model::auth::user::{ConnectAccount, User, UserMode},
-    mongodb::bson,
+    model::auth::{
+        login_event::{LoginEvent, LoginMethod},
+        session::Session,
+        token::{RefreshToken, RevokedToken},
+        user::{ConnectAccount, ConnectType, User, UserMode},
+    },
+    mongodb::bson::{self, oid::ObjectId, DateTime},
     Database,
 };
-use rocket::{response::status::BadRequest, serde::json::Json, State};
-use util::{jwt::create_jwt_token, oauth::OAuthData, util::create_exp};
+use rocket::{http::Status, serde::json::Json, State};
+use util::{
+    bcrypt::verify_password,
+    jwt::{create_jwt_token, verify_token, TokenPurpose},
+    oauth::{create_code_challenge, create_code_verifier, OAuthAccountInfo, OAuthData},
+    user_agent::device_name,
+    util::{create_exp, create_random_token, hash_token},
+};
 
 use crate::data::{
-    auth_data::{Claims, Token},
+    auth_data::{
+        AuthError, Claims, LinkClaims, LinkToken, LoginToken, LoginUserData, MfaClaims, MfaToken,
+        OAuthStateClaims, Token, ACCESS_TOKEN_EXP, LINK_TOKEN_EXP, LOGIN_EVENT_EXP, MFA_TOKEN_EXP,
+        OAUTH_STATE_EXP, REFRESH_TOKEN_EXP,
+    },
     code::Code,
     response::Response,
 };
 
-use super::data::{CreateUserInfo, RequestIp};
-use database::mongodb::options::FindOneAndUpdateOptions;
+use super::data::{CreateUserInfo, RequestClient};
+use database::mongodb::options::UpdateOptions;
 use database::{Collection, Error};
 
+/// Create the OAuth url with a signed `state` and the PKCE code challenge
+///
+/// The state is bound to `user_id` when linking the account to the login user.
+#[doc(hidden)]
+pub fn create_oauth_url(oauth: &OAuthData, private_key: &str, user_id: Option<String>) -> String {
+    let state = create_jwt_token(
+        private_key.as_bytes(),
+        OAuthStateClaims {
+            exp: create_exp(OAUTH_STATE_EXP),
+            purpose: TokenPurpose::OAuthState,
+            nonce: create_random_token(32),
+            account_type: oauth.provider.account_type(),
+            redirect_uri: oauth.redirect_uri.clone(),
+            user_id,
+        },
+    )
+    .unwrap();
+
+    let code_verifier = create_code_verifier(private_key.as_bytes(), &state);
+
+    oauth.get_auth_url(&state, &create_code_challenge(&code_verifier))
+}
+
+/// Verify the OAuth `state` of the callback
+///
+/// return the [OAuthStateClaims] of the state
+#[doc(hidden)]
+pub fn verify_oauth_state(
+    state: &str,
+    account_type: ConnectType,
+    user_id: Option<&str>,
+    public_key: &str,
+) -> Result<OAuthStateClaims, (Status, Json<Response<String>>)> {
+    let oauth_state_error = || {
+        (
+            Status::BadRequest,
+            Response::new(Code::OAuthStateError, None),
+        )
+    };
+
+    let claims = verify_token::<OAuthStateClaims>(state.to_string(), public_key.as_bytes())
+        .map_err(|_| oauth_state_error())?
+        .claims;
+
+    if claims.account_type != account_type || claims.user_id.as_deref() != user_id {
+        return Err(oauth_state_error());
+    }
+
+    Ok(claims)
+}
+
+/// Exchange the OAuth code for the account info of the provider
+#[doc(hidden)]
+pub async fn get_oauth_account_info(
+    oauth: &OAuthData<'_>,
+    code: String,
+    state: &str,
+    private_key: &str,
+) -> Result<OAuthAccountInfo, (Status, Json<Response<String>>)> {
+    let code_verifier = create_code_verifier(private_key.as_bytes(), state);
+
+    let data = oauth
+        .authorization_code(code, &code_verifier)
+        .await
+        .map_err(|_| {
+            (
+                Status::BadRequest,
+                Response::new(Code::OAuthCodeError, None),
+            )
+        })?;
+
+    oauth.provider.get_account_info(&data).await.map_err(|_| {
+        (
+            Status::BadRequest,
+            Response::new(Code::OAuthGetUserInfoError, None),
+        )
+    })
+}
+
 pub async fn connect_account(
-    oauth: OAuthData,
+    oauth: OAuthData<'_>,
     code: String,
+    state: String,
     db: &State<Database>,
     private_key: String,
-    request_ip: RequestIp,
-) -> Result<Json<Response<Token>>, BadRequest<Json<Response<String>>>> {
-    let data = oauth
-        .authorization_code(code)
-        .await
-        .map_err(|_| BadRequest(Some(Response::new(Code::OAuthCodeError, None))))?;
+    request_client: RequestClient,
+) -> Result<Json<Response<LoginToken>>, (Status, Json<Response<String>>)> {
+    let login_user_info = get_oauth_account_info(&oauth, code, &state, &private_key).await?;
 
-    let login_user_info = data
-        .get_account_info(&oauth.account_type)
-        .await
-        .map_err(|_| BadRequest(Some(Response::new(Code::OAuthGetUserInfoError, None))))?;
-
-    let user_data = create_and_update_user_info(
-        db.user.as_ref().unwrap(),
-        Some(ConnectAccount {
-            account_type: oauth.account_type.clone(),
-            name: login_user_info.name.clone(),
-            email: login_user_info.email.clone(),
-        }),
-        vec![],
-        None,
-        CreateUserInfo {
-            username: login_user_info.name.clone(),
-            email: login_user_info.email.clone(),
-            ip: request_ip.0,
-            verified_email: login_user_info.verified_email,
-        },
+    let user = db.user.as_ref().unwrap();
+    let connect = ConnectAccount {
+        account_type: oauth.provider.account_type(),
+        subject: Some(login_user_info.id.clone()),
+        name: login_user_info.name.clone(),
+        email: login_user_info.email.clone(),
+        avatar: Some(login_user_info.picture.clone()).filter(|picture| !picture.is_empty()),
+        last_login: Some(DateTime::now()),
+        school_id: login_user_info.school_id.clone(),
+        grade: login_user_info.grade.clone(),
+    };
+
+    // Find the user by the stable user id of the provider, the email of the account may be changed
+    let connect_filter = doc! {
+        "connects": {
+            "$elemMatch": {
+                "account_type": bson::to_bson(&connect.account_type).unwrap(),
+                "subject": &login_user_info.id
+            }
+        }
+    };
+
+    let user_data = match user.find_one(connect_filter.clone(), None).await.unwrap() {
+        Some(user_data) => {
+            user.update_one(
+                connect_filter,
+                doc! {
+                    "$set": {
+                        "connects.$": bson::to_bson(&connect).unwrap()
+                    }
+                },
+                None,
+            )
+            .await
+            .unwrap();
+
+            user_data
+        }
+        None => {
+            let existing_user = user
+                .find_one(doc! { "email": &login_user_info.email }, None)
+                .await
+                .unwrap();
+
+            if let Some(existing_user) = existing_user {
+                if !login_user_info.verified_email {
+                    // The provider doesn't prove the email belongs to the user,
+                    // the user must confirm the account with the password or the email.
+                    let link_token = create_jwt_token(
+                        private_key.as_bytes(),
+                        LinkClaims {
+                            exp: create_exp(LINK_TOKEN_EXP),
+                            purpose: TokenPurpose::Link,
+                            id: existing_user._id.to_string(),
+                            connect: ConnectAccount {
+                                last_login: None,
+                                ..connect
+                            },
+                        },
+                    )
+                    .unwrap();
+
+                    return Ok(Response::new(
+                        Code::LinkRequired,
+                        Some(LoginToken::LinkToken(LinkToken { link_token })),
+                    ));
+                }
+
+                if !existing_user.verified_email {
+                    // The email is proved by the provider, but the existing user never proved it,
+                    // so the login methods of the existing user may be created by someone else.
+                    reset_login_methods(db, existing_user._id).await.unwrap();
+                }
+            }
+
+            let user_data = create_and_update_user_info(
+                user,
+                vec![],
+                None,
+                CreateUserInfo {
+                    username: login_user_info.name.clone(),
+                    email: login_user_info.email.clone(),
+                    verified_email: login_user_info.verified_email,
+                },
+            )
+            .await
+            .unwrap()
+            .0;
+
+            add_connect(user, user_data._id, &connect).await.unwrap();
+
+            user_data
+        }
+    };
+
+    let claims = Claims::new(
+        user_data._id.to_string(),
+        user_data.username.clone(),
+        user_data.verified_email,
+        user_data.modes.clone(),
+    );
+
+    // Response token.
+    create_login_token(
+        db,
+        &private_key,
+        &user_data,
+        claims,
+        LoginMethod::Connect(connect.account_type),
+        &request_client,
     )
     .await
-    .unwrap()
-    .unwrap_or(
-        db.user
+}
+
+/// Add the connected account to the user,
+/// the account connected before the provider user id was stored is replaced
+#[doc(hidden)]
+pub async fn add_connect(
+    user: &Collection<User>,
+    user_id: ObjectId,
+    connect: &ConnectAccount,
+) -> Result<(), Error> {
+    let result = user
+        .update_one(
+            doc! {
+                "_id": user_id,
+                "connects": {
+                    "$elemMatch": {
+                        "account_type": bson::to_bson(&connect.account_type).unwrap(),
+                        "subject": null
+                    }
+                }
+            },
+            doc! {
+                "$set": {
+                    "connects.$": bson::to_bson(connect).unwrap()
+                }
+            },
+            None,
+        )
+        .await?;
+
+    if result.matched_count == 0 {
+        user.update_one(
+            doc! { "_id": user_id },
+            doc! {
+                "$push": {
+                    "connects": bson::to_bson(connect).unwrap()
+                }
+            },
+            None,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Check the account of the provider is not linked to any user
+#[doc(hidden)]
+pub async fn is_connect_linked(
+    user: &Collection<User>,
+    connect: &ConnectAccount,
+) -> Result<bool, Error> {
+    Ok(user
+        .find_one(
+            doc! {
+                "connects": {
+                    "$elemMatch": {
+                        "account_type": bson::to_bson(&connect.account_type).unwrap(),
+                        "subject": &connect.subject
+                    }
+                }
+            },
+            None,
+        )
+        .await?
+        .is_some())
+}
+
+/// Remove the login methods and the sessions of the unverified user, and verify the email
+#[doc(hidden)]
+pub async fn reset_login_methods(db: &Database, user_id: ObjectId) -> Result<(), Error> {
+    db.user
+        .as_ref()
+        .unwrap()
+        .update_one(
+            doc! { "_id": user_id },
+            doc! {
+                "$set": {
+                    "verified_email": true,
+                    "password_hash": null,
+                    "totp_secret": null,
+                    "totp_pending_secret": null,
+                    "recovery_codes": [],
+                    "passkeys": [],
+                    "connects": []
+                }
+            },
+            None,
+        )
+        .await?;
+
+    revoke_all_sessions(db, user_id).await
+}
+
+/// Create the login token, or a [MfaToken] if the user enabled two-factor authentication
+///
+/// The user scheduled for deletion can't login.
+/// The successful login of `method` is recorded when the token is created, not before the second factor.
+#[doc(hidden)]
+pub async fn create_login_token(
+    db: &Database,
+    private_key: &str,
+    user: &User,
+    claims: Claims,
+    method: LoginMethod,
+    request_client: &RequestClient,
+) -> Result<Json<Response<LoginToken>>, (Status, Json<Response<String>>)> {
+    if user.delete_at.is_some() {
+        return Err((
+            Status::Forbidden,
+            Response::new(Code::AccountPendingDeletion, None),
+        ));
+    }
+    if user.suspended_at.is_some() {
+        return Err((
+            Status::Forbidden,
+            Response::new(Code::AccountSuspended, None),
+        ));
+    }
+
+    if user.totp_secret.is_some() {
+        let mfa_token = create_jwt_token(
+            private_key.as_bytes(),
+            MfaClaims {
+                exp: create_exp(MFA_TOKEN_EXP),
+                purpose: TokenPurpose::Mfa,
+                id: claims.id,
+                auth_time: claims.auth_time,
+                method,
+            },
+        )
+        .unwrap();
+
+        return Ok(Response::new(
+            Code::MfaRequired,
+            Some(LoginToken::MfaToken(MfaToken { mfa_token })),
+        ));
+    }
+
+    record_login_event(db, user._id, method, true, request_client)
+        .await
+        .unwrap();
+
+    let token = create_token(db, private_key, claims, None, request_client)
+        .await
+        .unwrap();
+
+    Ok(Response::new(Code::Ok, Some(LoginToken::Token(token))))
+}
+
+/// Create a access token and a new refresh token
+///
+/// The refresh token joins `family` when rotating an existing refresh token,
+/// otherwise a new token family is created with a new session.
+#[doc(hidden)]
+pub async fn create_token(
+    db: &Database,
+    private_key: &str,
+    mut claims: Claims,
+    family: Option<ObjectId>,
+    request_client: &RequestClient,
+) -> Result<Token, Error> {
+    let user_id = ObjectId::parse_str(&claims.id).unwrap();
+    let is_new_session = family.is_none();
+    let family = family.unwrap_or_default();
+    let auth_time = claims.auth_time as i64;
+    claims.sid = family.to_hex();
+
+    let token = create_jwt_token(private_key.as_bytes(), claims).unwrap();
+
+    let now = DateTime::now();
+    let exp = DateTime::from_millis(create_exp(REFRESH_TOKEN_EXP) as i64 * 1000);
+    let device_name = device_name(request_client.user_agent.as_deref().unwrap_or_default());
+    let session = db.session.as_ref().unwrap();
+
+    if is_new_session {
+        session
+            .insert_one(
+                Session {
+                    _id: family,
+                    user_id,
+                    device_name,
+                    ip: request_client.ip.clone(),
+                    user_agent: request_client.user_agent.clone(),
+                    created_at: now,
+                    last_seen: now,
+                    exp,
+                },
+                None,
+            )
+            .await?;
+    } else {
+        session
+            .update_one(
+                doc! { "_id": family },
+                doc! {
+                    "$set": {
+                        "device_name": device_name,
+                        "ip": &request_client.ip,
+                        "user_agent": &request_client.user_agent,
+                        "last_seen": now,
+                        "exp": exp
+                    }
+                },
+                None,
+            )
+            .await?;
+    }
+
+    let new_refresh_token = create_random_token(64);
+    db.refresh_token
+        .as_ref()
+        .unwrap()
+        .insert_one(
+            RefreshToken {
+                _id: ObjectId::new(),
+                user_id,
+                family,
+                token_hash: hash_token(&new_refresh_token),
+                used: false,
+                revoked: false,
+                auth_time,
+                exp,
+            },
+            None,
+        )
+        .await?;
+
+    Ok(Token {
+        token,
+        refresh_token: new_refresh_token,
+    })
+}
+
+/// Revoke the access tokens and the refresh tokens of the login session
+///
+/// All the access tokens of the session `sid` are revoked, not only the token `jti` used to logout.
+#[doc(hidden)]
+pub async fn revoke_session(
+    db: &Database,
+    user_id: ObjectId,
+    jti: String,
+    sid: &str,
+    exp: usize,
+) -> Result<(), Error> {
+    let family = ObjectId::parse_str(sid).ok();
+
+    // The access tokens of the session are expired after the access token expiration time
+    db.revoked_token
+        .as_ref()
+        .unwrap()
+        .insert_one(
+            RevokedToken {
+                _id: ObjectId::new(),
+                user_id,
+                jti: Some(jti),
+                sid: family.map(|family| family.to_hex()),
+                issued_before: None,
+                exp: DateTime::from_millis(create_exp(ACCESS_TOKEN_EXP).max(exp) as i64 * 1000),
+            },
+            None,
+        )
+        .await?;
+
+    if let Some(family) = family {
+        db.refresh_token
             .as_ref()
             .unwrap()
-            .find_one(
+            .update_many(
+                doc! { "family": family, "user_id": user_id },
                 doc! {
-                    "email": &login_user_info.email
+                    "$set": {
+                        "revoked": true
+                    }
                 },
                 None,
             )
-            .await
+            .await?;
+
+        db.session
+            .as_ref()
             .unwrap()
-            .unwrap(),
-    );
+            .delete_one(doc! { "_id": family, "user_id": user_id }, None)
+            .await?;
+    }
 
-    let token = create_jwt_token(
-        private_key.as_bytes(),
-        Claims {
-            exp: create_exp(60 * 60 * 24 * 7),
-            username: login_user_info.name,
-            id: user_data._id.to_string(),
-            verified_email: login_user_info.verified_email,
-            modes: vec![UserMode::Student],
-        },
-    )
-    .unwrap();
+    Ok(())
+}
 
-    // Response token.
-    Ok(Response::new(Code::Ok, Some(Token { token })))
+/// Revoke the access tokens and the refresh tokens of the session `family` of the user
+///
+/// return `false` if the session is not found
+#[doc(hidden)]
+pub async fn revoke_session_by_id(
+    db: &Database,
+    user_id: ObjectId,
+    family: ObjectId,
+) -> Result<bool, Error> {
+    let result = db
+        .session
+        .as_ref()
+        .unwrap()
+        .delete_one(doc! { "_id": family, "user_id": user_id }, None)
+        .await?;
+
+    if result.deleted_count == 0 {
+        return Ok(false);
+    }
+
+    // The access tokens of the session are expired after the access token expiration time
+    db.revoked_token
+        .as_ref()
+        .unwrap()
+        .insert_one(
+            RevokedToken {
+                _id: ObjectId::new(),
+                user_id,
+                jti: None,
+                sid: Some(family.to_hex()),
+                issued_before: None,
+                exp: DateTime::from_millis(create_exp(ACCESS_TOKEN_EXP) as i64 * 1000),
+            },
+            None,
+        )
+        .await?;
+
+    db.refresh_token
+        .as_ref()
+        .unwrap()
+        .update_many(
+            doc! { "family": family, "user_id": user_id },
+            doc! {
+                "$set": {
+                    "revoked": true
+                }
+            },
+            None,
+        )
+        .await?;
+
+    Ok(true)
+}
+
+/// Revoke all access tokens and refresh tokens of the user
+#[doc(hidden)]
+pub async fn revoke_all_sessions(db: &Database, user_id: ObjectId) -> Result<(), Error> {
+    let now = create_exp(0);
+
+    // The tokens issued before now are expired after the access token expiration time
+    db.revoked_token
+        .as_ref()
+        .unwrap()
+        .insert_one(
+            RevokedToken {
+                _id: ObjectId::new(),
+                user_id,
+                jti: None,
+                sid: None,
+                issued_before: Some(now as i64),
+                exp: DateTime::from_millis((now + ACCESS_TOKEN_EXP) as i64 * 1000),
+            },
+            None,
+        )
+        .await?;
+
+    db.refresh_token
+        .as_ref()
+        .unwrap()
+        .update_many(
+            doc! { "user_id": user_id },
+            doc! {
+                "$set": {
+                    "revoked": true
+                }
+            },
+            None,
+        )
+        .await?;
+
+    db.session
+        .as_ref()
+        .unwrap()
+        .delete_many(doc! { "user_id": user_id }, None)
+        .await?;
+
+    Ok(())
+}
+
+/// Record a login attempt of the user
+#[doc(hidden)]
+pub async fn record_login_event(
+    db: &Database,
+    user_id: ObjectId,
+    method: LoginMethod,
+    success: bool,
+    request_client: &RequestClient,
+) -> Result<(), Error> {
+    let now = DateTime::now();
+
+    db.login_events
+        .as_ref()
+        .unwrap()
+        .insert_one(
+            LoginEvent {
+                _id: ObjectId::new(),
+                user_id,
+                time: now,
+                ip: request_client.ip.clone(),
+                user_agent: request_client.user_agent.clone(),
+                method,
+                success,
+                exp: DateTime::from_millis(now.timestamp_millis() + LOGIN_EVENT_EXP * 1000),
+            },
+            None,
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Find the user of the login token
+#[doc(hidden)]
+pub async fn find_login_user(
+    login_user_data: Result<LoginUserData, AuthError>,
+    db: &Database,
+) -> Result<User, (Status, Json<Response<String>>)> {
+    // Check the user is logged in.
+    let login_user_data = match login_user_data {
+        Ok(login_user_data) => login_user_data,
+        Err(err) => return Err((Status::Unauthorized, err.0.unwrap())),
+    };
+
+    db.user
+        .as_ref()
+        .unwrap()
+        .find_one(
+            doc! {
+                "_id": ObjectId::parse_str(login_user_data.id).unwrap()
+            },
+            None,
+        )
+        .await
+        .unwrap()
+        .ok_or_else(|| {
+            (
+                Status::Unauthorized,
+                Response::new(Code::LoginUserNotFoundError, None),
+            )
+        })
+}
+
+/// Verify the current password if the user has a password,
+/// otherwise the user must have logged in within 10 minutes.
+#[doc(hidden)]
+pub fn verify_reauthentication(
+    user: &User,
+    password: &Option<String>,
+    auth_time: usize,
+) -> Result<(), (Status, Json<Response<String>>)> {
+    if let Some(password_hash) = &user.password_hash {
+        // verify password correctness
+        let password = password.clone().unwrap_or_default();
+        if !verify_password(password_hash.clone(), &password).unwrap() {
+            // Response input password error.
+            return Err((
+                Status::Unauthorized,
+                Response::new(Code::LoginPasswordError, None),
+            ));
+        }
+    } else if create_exp(0) > auth_time + 60 * 10 {
+        // The user without password must login again by OAuth.
+        return Err((
+            Status::Unauthorized,
+            Response::new(Code::ReauthenticationRequired, None),
+        ));
+    }
+
+    Ok(())
 }
 
-/// Update user info if it exists else insert
+/// Insert the user if the email is not registered
+///
+/// return the user of the email and whether the user is inserted,
+/// the existing user is not changed.
 #[doc(hidden)]
 pub async fn create_and_update_user_info(
     user: &Collection<User>,
-    connect: Option<ConnectAccount>,
     modes: Vec<UserMode>,
     password_hash: Option<String>,
     user_info: CreateUserInfo,
-) -> Result<Option<User>, Error> {
-    let mut option = FindOneAndUpdateOptions::default();
+) -> Result<(User, bool), Error> {
+    let mut option = UpdateOptions::default();
     option.upsert = Some(true);
 
     // insert user info if not exists
-    user.find_one_and_update(
-        doc! { "email": &user_info.email },
-        doc! {
-            "$setOnInsert": {
-                "username": &user_info.username,
-                "email": &user_info.email,
-                "verified_email": &user_info.verified_email,
-                "modes": [],
-                "login_ips": [],
-                "password_hash": password_hash,
-                "connects": []
-            }
-        },
-        option,
-    )
-    .await?;
-
-    // add login ip and modes
-    user.update_one(
-        doc! { "email": &user_info.email },
-        doc! {
-            "$addToSet": {
-                "login_ips": &user_info.ip,
-                "modes": {
-                    "$each": bson::to_bson(&modes).unwrap()
-                },
-            }
-        },
-        None,
-    )
-    .await?;
-
-    if let Some(connect) = connect {
-        user.update_one(
+    let result = user
+        .update_one(
             doc! { "email": &user_info.email },
             doc! {
-                "$addToSet": {
-                    "connects": bson::to_bson(&connect).unwrap()
+                "$setOnInsert": {
+                    "username": &user_info.username,
+                    "email": &user_info.email,
+                    "verified_email": &user_info.verified_email,
+                    "modes": bson::to_bson(&modes).unwrap(),
+                    "password_hash": password_hash,
+                    "connects": []
                 }
             },
-            None,
+            option,
         )
         .await?;
-    }
 
     let user_data = user
         .find_one(doc! { "email": &user_info.email }, None)
-        .await?;
+        .await?
+        .unwrap();
 
-    Ok(user_data)
+    Ok((user_data, result.upserted_id.is_some()))
 }
@@ -1,3 +1,5 @@
+/// Admin APIs
+mod admin;
 /// Authenticate APIs
 mod authentication;
 mod user;
@@ -9,8 +11,20 @@ use rocket::fairing::AdHoc;
 pub fn stage() -> AdHoc {
     AdHoc::on_ignite("load api stage", |rocket| async {
         rocket
+            .attach(admin::api::stage())
+            .attach(admin::audit::stage())
+            .attach(admin::teacher::stage())
             .attach(authentication::api::stage())
             .attach(verify_email::stage())
             .attach(user::api::stage())
+            .attach(user::connect::stage())
+            .attach(user::delete::stage())
+            .attach(user::lockout::stage())
+            .attach(user::export::stage())
+            .attach(user::magic_link::stage())
+            .attach(user::mfa::stage())
+            .attach(user::passkey::stage())
+            .attach(user::security::stage())
+            .attach(user::teacher::stage())
     })
 }
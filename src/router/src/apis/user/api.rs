@@ -1,39 +1,62 @@
-use crate::apis::authentication::data::{CreateUserInfo, RequestIp};
-use crate::apis::authentication::util::create_and_update_user_info;
+use crate::apis::authentication::data::{CreateUserInfo, RequestClient};
+use crate::apis::authentication::util::{
+    create_and_update_user_info, create_login_token, create_token, find_login_user,
+    record_login_event, revoke_all_sessions, revoke_session, verify_reauthentication,
+};
+use crate::apis::user::lockout::{
+    is_login_locked, record_login_failure, reset_login_failures, send_unlock_code,
+};
 use crate::data::auth_data::{
-    AuthError, Claims, EditUserData, LoginFromData, LoginUserData, SignUp, Token,
+    AuthError, ChangeEmailConfirmData, ChangeEmailData, ChangePasswordData, Claims, EditUserData,
+    ForgotPasswordData, LoginFromData, LoginToken, LoginUserData, RefreshTokenData,
+    ResetPasswordData, SignUp, Token, CHANGE_EMAIL_EXP, RESET_PASSWORD_EXP, VERIFY_EMAIL_EXP,
+    VERIFY_EMAIL_RESEND_INTERVAL,
 };
 use crate::data::code::Code;
 use crate::data::response::Response;
 use crate::data::user::UserInfo;
 use crate::Config;
-use database::model::auth::user::UserMode;
-use database::mongodb::bson;
-use database::mongodb::options::FindOneAndUpdateOptions;
-use database::{doc, mongodb::bson::oid::ObjectId, Database};
+use database::model::auth::login_event::LoginMethod;
+use database::model::auth::user::{Role, UserMode};
+use database::mongodb::bson::{self, DateTime};
+use database::mongodb::options::{FindOneAndUpdateOptions, ReturnDocument};
+use database::{doc, is_duplicate_key_error, mongodb::bson::oid::ObjectId, Database};
 use rocket::fairing::AdHoc;
 use rocket::form::Form;
 use rocket::http::Status;
 use rocket::response::status::{Conflict, Unauthorized};
 use rocket::serde::json::Json;
 use rocket::State;
-use util::bcrypt::password_hash;
-use util::email::{send_verify_email, VerifyEmailClaims};
-use util::jwt::create_jwt_token;
-use util::util::create_exp;
+use util::bcrypt::{password_hash, verify_password};
+use util::email::{
+    send_change_email_confirm_email, send_email_change_requested_email,
+    send_password_changed_email, send_reset_password_email, send_verify_email, ChangeEmailClaims,
+    ResetPasswordClaims, VerifyEmailClaims,
+};
+use util::jwt::{create_jwt_token, verify_token, TokenPurpose};
+use util::password::is_strong_password;
+use util::role::roles_include;
+use util::util::{create_exp, hash_token};
 
 /// # User login API
+/// The same error is responded whether the email is registered or not.
+/// The email and the ip are temporarily locked after too many failed logins,
+/// and a unlock link is sent to the email, see `/user/login/unlock`.
 /// ## Request
 /// - Path `/user/login`
 /// - Method: `POST`
 /// - FromData [LoginFromData]
 /// ## Response
 /// - Code
-///     - [Code::LoginUserNotFoundError]
-///     - [Code::LoginPasswordError] - Input password error.
+///     - [Code::LoginCredentialsError] - The email or the password is incorrect.
+///     - [Code::LoginLocked] - Too many failed logins of the email or the ip.
+///     - [Code::MfaRequired] - Two-factor authentication is required, see `/user/login/mfa`.
+///     - [Code::AccountPendingDeletion]
+///     - [Code::AccountSuspended]
 ///     - [Code::Ok]
 /// - Content
-///     - [Token] - A JWT token.
+///     - [Token] - A JWT token and a refresh token.
+///     - [MfaToken](crate::data::auth_data::MfaToken) - If the code is [Code::MfaRequired].
 /// ## Curl Example
 /// ```bash
 /// curl -X POST -F email=aijdfajodwsdf@gmail.com -F password=123 http://127.0.0.1:8000/user/login
@@ -43,14 +66,495 @@ async fn login(
     login_info: Form<LoginFromData>,
     db: &State<Database>,
     config: &State<Config>,
+    request_client: RequestClient,
+) -> Result<Json<Response<LoginToken>>, (Status, Json<Response<String>>)> {
+    // The same email is used to find the user and as the key of the lockout.
+    let email = login_info.email.trim();
+
+    if is_login_locked(db, email, &request_client.ip)
+        .await
+        .unwrap()
+    {
+        return Err((
+            Status::TooManyRequests,
+            Response::new(Code::LoginLocked, None),
+        ));
+    }
+
+    let find_user = db
+        .user
+        .as_ref()
+        .unwrap()
+        .find_one(
+            doc! {
+                "email": email,
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    let verified = match find_user
+        .as_ref()
+        .and_then(|user_data| user_data.password_hash.clone())
+    {
+        // verify password correctness
+        Some(password_hash) => verify_password(password_hash, &login_info.password).unwrap(),
+        None => {
+            // Take the same time as verifying a password, so the registered emails can't be found by the time.
+            password_hash(&login_info.password).unwrap();
+            false
+        }
+    };
+
+    let find_user = match find_user {
+        Some(user_data) if verified => user_data,
+        _ => {
+            if let Some(user_data) = &find_user {
+                record_login_event(
+                    db,
+                    user_data._id,
+                    LoginMethod::Password,
+                    false,
+                    &request_client,
+                )
+                .await
+                .unwrap();
+            }
+
+            let locked = record_login_failure(db, email, &request_client.ip)
+                .await
+                .unwrap();
+
+            if let (true, Some(user_data)) = (locked, &find_user) {
+                send_unlock_code(config, &user_data.email);
+            }
+
+            // Response the email or the password is incorrect.
+            return Err((
+                Status::Unauthorized,
+                Response::new(Code::LoginCredentialsError, None),
+            ));
+        }
+    };
+
+    reset_login_failures(db, email).await.unwrap();
+
+    let claims = Claims::new(
+        find_user._id.to_string(),
+        find_user.username.clone(),
+        find_user.verified_email,
+        find_user.modes.clone(),
+    );
+
+    // Response JWT.
+    create_login_token(
+        db,
+        &config.private_key,
+        &find_user,
+        claims,
+        LoginMethod::Password,
+        &request_client,
+    )
+    .await
+}
+
+/// # Refresh the login token
+/// Every refresh token can only be used once, a new refresh token is returned with the new JWT.
+/// If a used refresh token is presented again, all refresh tokens of the same login are revoked.
+/// ## Request
+/// - Path `/user/token/refresh`
+/// - Method: `POST`
+/// - FromData [RefreshTokenData]
+/// ## Response
+/// - Code
+///     - [Code::RefreshTokenError]
+///     - [Code::RefreshTokenReused]
+///     - [Code::LoginUserNotFoundError]
+///     - [Code::AccountPendingDeletion]
+///     - [Code::AccountSuspended]
+///     - [Code::Ok]
+/// - Content
+///     - [Token] - A JWT token and a refresh token.
+/// ## Curl Example
+/// ```bash
+/// curl -X POST -F refresh_token={refresh_token} http://127.0.0.1:8000/user/token/refresh
+/// ```
+#[post("/token/refresh", data = "<refresh_token_data>")]
+async fn refresh_token(
+    refresh_token_data: Form<RefreshTokenData>,
+    db: &State<Database>,
+    config: &State<Config>,
+    request_client: RequestClient,
 ) -> Result<Json<Response<Token>>, (Status, Json<Response<String>>)> {
+    let refresh_tokens = db.refresh_token.as_ref().unwrap();
+
+    let find_token = if let Some(token_data) = refresh_tokens
+        .find_one(
+            doc! {
+                "token_hash": hash_token(&refresh_token_data.refresh_token),
+            },
+            None,
+        )
+        .await
+        .unwrap()
+    {
+        token_data
+    } else {
+        // Response refresh token not found.
+        return Err((
+            Status::Unauthorized,
+            Response::new(Code::RefreshTokenError, None),
+        ));
+    };
+
+    if find_token.revoked || find_token.exp < DateTime::now() {
+        // Response refresh token is revoked or expired.
+        return Err((
+            Status::Unauthorized,
+            Response::new(Code::RefreshTokenError, None),
+        ));
+    }
+
+    // Mark the token as used, only one request can rotate the token.
+    let rotated = refresh_tokens
+        .find_one_and_update(
+            doc! {
+                "_id": find_token._id,
+                "used": false,
+            },
+            doc! {
+                "$set": {
+                    "used": true
+                }
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    if rotated.is_none() {
+        // The token has already been used, the token family may be stolen.
+        refresh_tokens
+            .update_many(
+                doc! { "family": find_token.family },
+                doc! {
+                    "$set": {
+                        "revoked": true
+                    }
+                },
+                None,
+            )
+            .await
+            .unwrap();
+
+        return Err((
+            Status::Unauthorized,
+            Response::new(Code::RefreshTokenReused, None),
+        ));
+    }
+
+    let find_user = if let Some(user_data) = db
+        .user
+        .as_ref()
+        .unwrap()
+        .find_one(doc! { "_id": find_token.user_id }, None)
+        .await
+        .unwrap()
+    {
+        user_data
+    } else {
+        // Response user not found.
+        return Err((
+            Status::Unauthorized,
+            Response::new(Code::LoginUserNotFoundError, None),
+        ));
+    };
+
+    if find_user.delete_at.is_some() {
+        return Err((
+            Status::Forbidden,
+            Response::new(Code::AccountPendingDeletion, None),
+        ));
+    }
+    if find_user.suspended_at.is_some() {
+        return Err((
+            Status::Forbidden,
+            Response::new(Code::AccountSuspended, None),
+        ));
+    }
+
+    let mut claims = Claims::new(
+        find_user._id.to_string(),
+        find_user.username,
+        find_user.verified_email,
+        find_user.modes,
+    );
+    // Keep the login time of the session.
+    claims.auth_time = find_token.auth_time as usize;
+
+    let token = create_token(
+        db,
+        &config.private_key,
+        claims,
+        Some(find_token.family),
+        &request_client,
+    )
+    .await
+    .unwrap();
+
+    // Response JWT.
+    Ok(Response::new(Code::Ok, Some(token)))
+}
+
+/// # Logout the current session
+/// Revoke the login tokens and the refresh tokens of the current session.
+/// ## Request
+/// - Path `/user/logout`
+/// - Method: `POST`
+/// - [X] Authorization
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::AuthError]
+/// ## Curl Example
+/// ```bash
+/// curl -X POST -H "Authorization: Bearer {Token}" http://<host>/user/logout
+/// ```
+#[post("/logout")]
+async fn logout(
+    login_user_data: Result<LoginUserData, AuthError>,
+    db: &State<Database>,
+) -> Result<Json<Response<String>>, AuthError> {
+    // Check the user is logged in.
+    let login_user_data = match login_user_data {
+        Ok(login_user_data) => login_user_data,
+        Err(err) => return Err(err),
+    };
+
+    revoke_session(
+        db,
+        ObjectId::parse_str(&login_user_data.id).unwrap(),
+        login_user_data.jti,
+        &login_user_data.sid,
+        login_user_data.exp,
+    )
+    .await
+    .unwrap();
+
+    // Response Ok.
+    Ok(Response::new(Code::Ok, None))
+}
+
+/// # Logout all sessions
+/// Revoke all login tokens and refresh tokens of the user.
+/// ## Request
+/// - Path `/user/logout-all`
+/// - Method: `POST`
+/// - [X] Authorization
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::AuthError]
+/// ## Curl Example
+/// ```bash
+/// curl -X POST -H "Authorization: Bearer {Token}" http://<host>/user/logout-all
+/// ```
+#[post("/logout-all")]
+async fn logout_all(
+    login_user_data: Result<LoginUserData, AuthError>,
+    db: &State<Database>,
+) -> Result<Json<Response<String>>, AuthError> {
+    // Check the user is logged in.
+    let login_user_data = match login_user_data {
+        Ok(login_user_data) => login_user_data,
+        Err(err) => return Err(err),
+    };
+
+    revoke_all_sessions(db, ObjectId::parse_str(&login_user_data.id).unwrap())
+        .await
+        .unwrap();
+
+    // Response Ok.
+    Ok(Response::new(Code::Ok, None))
+}
+
+/// # Forgot password API
+/// Send a reset password link to the email if the email is registered.
+/// ## Request
+/// - Path `/user/password/forgot`
+/// - Method: `POST`
+/// - FromData [ForgotPasswordData]
+/// ## Response
+/// - Code
+///     - [Code::Ok] - Always responded, whether the email is registered or not.
+/// ## Curl Example
+/// ```bash
+/// curl -X POST -F email=aijdfajodwsdf@gmail.com http://<host>/user/password/forgot
+/// ```
+#[post("/password/forgot", data = "<forgot_password_data>")]
+async fn forgot_password(
+    forgot_password_data: Form<ForgotPasswordData>,
+    db: &State<Database>,
+    config: &State<Config>,
+) -> Json<Response<String>> {
+    if let Some(user_data) = db
+        .user
+        .as_ref()
+        .unwrap()
+        .find_one(doc! { "email": &forgot_password_data.email }, None)
+        .await
+        .unwrap()
+    {
+        // The reset password code.
+        let code = create_jwt_token(
+            config.private_key.as_bytes(),
+            ResetPasswordClaims {
+                exp: create_exp(RESET_PASSWORD_EXP),
+                purpose: TokenPurpose::ResetPassword,
+                email: user_data.email.clone(),
+                password: hash_token(&user_data.password_hash.unwrap_or_default()),
+            },
+        )
+        .unwrap();
+
+        send_reset_password_email(
+            &config.google_account_email,
+            &config.google_account_password,
+            &config.reset_password_url,
+            code,
+            &user_data.email,
+        );
+    }
+
+    // Response Ok.
+    Response::new(Code::Ok, None)
+}
+
+/// # Reset password API
+/// Set a new password by the code from the reset password email, and logout all sessions.
+/// ## Request
+/// - Path `/user/password/reset`
+/// - Method: `POST`
+/// - FromData [ResetPasswordData]
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::ResetPasswordError]
+///     - [Code::WeakPassword]
+/// ## Curl Example
+/// ```bash
+/// curl -X POST -F code={code} -F password=lipoic2022 http://<host>/user/password/reset
+/// ```
+#[post("/password/reset", data = "<reset_password_data>")]
+async fn reset_password(
+    reset_password_data: Form<ResetPasswordData>,
+    db: &State<Database>,
+    config: &State<Config>,
+) -> Result<Json<Response<String>>, (Status, Json<Response<String>>)> {
+    let reset_error = || {
+        (
+            Status::Unauthorized,
+            Response::new(Code::ResetPasswordError, None),
+        )
+    };
+
+    if !is_strong_password(&reset_password_data.password) {
+        return Err((Status::BadRequest, Response::new(Code::WeakPassword, None)));
+    }
+
+    let claims = verify_token::<ResetPasswordClaims>(
+        reset_password_data.code.clone(),
+        config.public_key.as_bytes(),
+    )
+    .map_err(|_| reset_error())?
+    .claims;
+
+    let find_user = db
+        .user
+        .as_ref()
+        .unwrap()
+        .find_one(doc! { "email": &claims.email }, None)
+        .await
+        .unwrap()
+        .ok_or_else(reset_error)?;
+
+    // The code is invalid after the password is changed.
+    if hash_token(find_user.password_hash.as_deref().unwrap_or_default()) != claims.password {
+        return Err(reset_error());
+    }
+
+    let new_password_hash = password_hash(&reset_password_data.password).unwrap();
+
+    // Only update if the password has not been changed by another request.
+    db.user
+        .as_ref()
+        .unwrap()
+        .find_one_and_update(
+            doc! {
+                "_id": find_user._id,
+                "password_hash": &find_user.password_hash,
+            },
+            doc! {
+                "$set": {
+                    "password_hash": new_password_hash,
+                    // The user has proved the ownership of the email.
+                    "verified_email": true,
+                }
+            },
+            None,
+        )
+        .await
+        .unwrap()
+        .ok_or_else(reset_error)?;
+
+    revoke_all_sessions(db, find_user._id).await.unwrap();
+
+    // Response Ok.
+    Ok(Response::new(Code::Ok, None))
+}
+
+/// # Change password API
+/// Change the password of the login user, or set a password if the user has no password.
+/// The current password is required if the user has a password,
+/// otherwise the user must have logged in within 10 minutes.
+/// ## Request
+/// - Path `/user/password`
+/// - Method: `PUT`
+/// - FromData [ChangePasswordData]
+/// - [X] Authorization
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::AuthError]
+///     - [Code::LoginUserNotFoundError]
+///     - [Code::LoginPasswordError] - Input password error.
+///     - [Code::ReauthenticationRequired]
+///     - [Code::WeakPassword]
+/// ## Curl Example
+/// ```bash
+/// curl -X PUT -H "Authorization: Bearer {Token}" -F password=lipoic2022 -F new_password=lipoic2023 http://<host>/user/password
+/// ```
+#[put("/password", data = "<change_password_data>")]
+async fn change_password(
+    change_password_data: Form<ChangePasswordData>,
+    login_user_data: Result<LoginUserData, AuthError>,
+    db: &State<Database>,
+    config: &State<Config>,
+) -> Result<Json<Response<String>>, (Status, Json<Response<String>>)> {
+    // Check the user is logged in.
+    let login_user_data = match login_user_data {
+        Ok(login_user_data) => login_user_data,
+        Err(err) => return Err((Status::Unauthorized, err.0.unwrap())),
+    };
+
     let find_user = if let Some(user_data) = db
         .user
         .as_ref()
         .unwrap()
         .find_one(
             doc! {
-                "email": &login_info.email,
+                "_id": ObjectId::parse_str(&login_user_data.id).unwrap()
             },
             None,
         )
@@ -66,37 +570,204 @@ async fn login(
         ));
     };
 
-    if let Some(password_hash) = find_user.password_hash {
-        // verify password correctness
-        if util::bcrypt::verify_password(password_hash, &login_info.password).unwrap() {
-            let token = create_jwt_token(
-                config.private_key.as_bytes(),
-                Claims {
-                    exp: create_exp(60 * 60 * 24 * 7),
-                    username: find_user.username,
-                    id: find_user._id.to_string(),
-                    verified_email: find_user.verified_email,
-                    modes: find_user.modes,
-                },
-            )
-            .unwrap();
+    verify_reauthentication(
+        &find_user,
+        &change_password_data.password,
+        login_user_data.auth_time,
+    )?;
 
-            // Response JWT.
-            Ok(Response::new(Code::Ok, Some(Token { token })))
-        } else {
-            // Response input password error.
-            Err((
-                Status::Unauthorized,
-                Response::new(Code::LoginPasswordError, None),
-            ))
-        }
-    } else {
-        // Response input password error.
-        Err((
+    if !is_strong_password(&change_password_data.new_password) {
+        return Err((Status::BadRequest, Response::new(Code::WeakPassword, None)));
+    }
+
+    db.user
+        .as_ref()
+        .unwrap()
+        .update_one(
+            doc! { "_id": find_user._id },
+            doc! {
+                "$set": {
+                    "password_hash": password_hash(&change_password_data.new_password).unwrap()
+                }
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    send_password_changed_email(
+        &config.google_account_email,
+        &config.google_account_password,
+        &find_user.email,
+    );
+
+    // Response Ok.
+    Ok(Response::new(Code::Ok, None))
+}
+
+/// # Change email API
+/// Send a confirmation link to the new email and a notice to the current email,
+/// the email is changed after the new email is confirmed, see `/user/email/confirm`.
+/// The current password is required if the user has a password,
+/// otherwise the user must have logged in within 10 minutes.
+/// ## Request
+/// - Path `/user/email`
+/// - Method: `PUT`
+/// - FromData [ChangeEmailData]
+/// - [X] Authorization
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::AuthError]
+///     - [Code::LoginUserNotFoundError]
+///     - [Code::LoginPasswordError] - Input password error.
+///     - [Code::ReauthenticationRequired]
+///     - [Code::SignUpEmailAlreadyRegistered]
+/// ## Curl Example
+/// ```bash
+/// curl -X PUT -H "Authorization: Bearer {Token}" -F email=lipoic@example.com -F password=lipoic2022 http://<host>/user/email
+/// ```
+#[put("/email", data = "<change_email_data>")]
+async fn change_email(
+    change_email_data: Form<ChangeEmailData>,
+    login_user_data: Result<LoginUserData, AuthError>,
+    db: &State<Database>,
+    config: &State<Config>,
+) -> Result<Json<Response<String>>, (Status, Json<Response<String>>)> {
+    let auth_time = login_user_data
+        .as_ref()
+        .map(|data| data.auth_time)
+        .unwrap_or_default();
+    let find_user = find_login_user(login_user_data, db).await?;
+
+    verify_reauthentication(&find_user, &change_email_data.password, auth_time)?;
+
+    if db
+        .user
+        .as_ref()
+        .unwrap()
+        .find_one(doc! { "email": &change_email_data.email }, None)
+        .await
+        .unwrap()
+        .is_some()
+    {
+        return Err((
+            Status::Conflict,
+            Response::new(Code::SignUpEmailAlreadyRegistered, None),
+        ));
+    }
+
+    // The change email code.
+    let code = create_jwt_token(
+        config.private_key.as_bytes(),
+        ChangeEmailClaims {
+            exp: create_exp(CHANGE_EMAIL_EXP),
+            purpose: TokenPurpose::ChangeEmail,
+            id: find_user._id.to_string(),
+            email: find_user.email.clone(),
+            new_email: change_email_data.email.clone(),
+        },
+    )
+    .unwrap();
+
+    send_change_email_confirm_email(
+        &config.google_account_email,
+        &config.google_account_password,
+        &config.change_email_url,
+        code,
+        &change_email_data.email,
+    );
+    send_email_change_requested_email(
+        &config.google_account_email,
+        &config.google_account_password,
+        &change_email_data.email,
+        &find_user.email,
+    );
+
+    // Response Ok.
+    Ok(Response::new(Code::Ok, None))
+}
+
+/// # Confirm change email API
+/// Change the email by the code from the confirmation email, the new email is verified.
+/// ## Request
+/// - Path `/user/email/confirm`
+/// - Method: `POST`
+/// - FromData [ChangeEmailConfirmData]
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::ChangeEmailError]
+///     - [Code::SignUpEmailAlreadyRegistered]
+/// ## Curl Example
+/// ```bash
+/// curl -X POST -F code={code} http://<host>/user/email/confirm
+/// ```
+#[post("/email/confirm", data = "<change_email_confirm_data>")]
+async fn confirm_change_email(
+    change_email_confirm_data: Form<ChangeEmailConfirmData>,
+    db: &State<Database>,
+    config: &State<Config>,
+) -> Result<Json<Response<String>>, (Status, Json<Response<String>>)> {
+    let change_email_error = || {
+        (
             Status::Unauthorized,
-            Response::new(Code::LoginPasswordError, None),
-        ))
+            Response::new(Code::ChangeEmailError, None),
+        )
+    };
+    let already_registered = || {
+        (
+            Status::Conflict,
+            Response::new(Code::SignUpEmailAlreadyRegistered, None),
+        )
+    };
+
+    let claims = verify_token::<ChangeEmailClaims>(
+        change_email_confirm_data.code.clone(),
+        config.public_key.as_bytes(),
+    )
+    .map_err(|_| change_email_error())?
+    .claims;
+
+    let user = db.user.as_ref().unwrap();
+
+    if user
+        .find_one(doc! { "email": &claims.new_email }, None)
+        .await
+        .unwrap()
+        .is_some()
+    {
+        return Err(already_registered());
     }
+
+    // The code is invalid after the email is changed.
+    // The unique index of the email rejects the email registered at the same time.
+    let result = match user
+        .update_one(
+            doc! {
+                "_id": ObjectId::parse_str(&claims.id).map_err(|_| change_email_error())?,
+                "email": &claims.email
+            },
+            doc! {
+                "$set": {
+                    "email": &claims.new_email,
+                    "verified_email": true
+                }
+            },
+            None,
+        )
+        .await
+    {
+        Err(err) if is_duplicate_key_error(&err) => return Err(already_registered()),
+        result => result.unwrap(),
+    };
+
+    if result.modified_count == 0 {
+        return Err(change_email_error());
+    }
+
+    // Response Ok.
+    Ok(Response::new(Code::Ok, None))
 }
 
 /// # Sign up account API
@@ -119,54 +790,144 @@ async fn sign_up(
     sign_up: Form<SignUp>,
     db: &State<Database>,
     config: &State<Config>,
-    request_ip: RequestIp,
 ) -> Result<Json<Response<String>>, Conflict<Json<Response<String>>>> {
     let password_hash = password_hash(&sign_up.password).unwrap();
 
-    let user_data = create_and_update_user_info(
+    // The teacher mode is only granted by the teacher verification.
+    let mut modes = sign_up.modes.0.clone();
+    modes.retain(|mode| *mode != UserMode::Teacher);
+
+    let inserted = match create_and_update_user_info(
         db.user.as_ref().unwrap(),
-        None,
-        sign_up.modes.0.clone(),
+        modes,
         Some(password_hash),
         CreateUserInfo {
             username: sign_up.username.clone(),
             email: sign_up.email.clone(),
-            ip: request_ip.0,
             verified_email: false,
         },
     )
     .await
-    .unwrap();
+    {
+        // The unique index of the email rejects the email registered at the same time.
+        Err(err) if is_duplicate_key_error(&err) => false,
+        result => result.unwrap().1,
+    };
 
-    if user_data.is_none() {
-        // The verify email code.
-        let code = create_jwt_token(
-            config.private_key.as_bytes(),
-            VerifyEmailClaims {
-                exp: create_exp(60 * 10),
-                email: sign_up.email.clone(),
-            },
+    if !inserted {
+        // Response email is already registered.
+        return Err(Conflict(Some(Response::new(
+            Code::SignUpEmailAlreadyRegistered,
+            None,
+        ))));
+    }
+
+    db.user
+        .as_ref()
+        .unwrap()
+        .update_one(
+            doc! { "email": &sign_up.email },
+            doc! { "$set": { "verify_email_sent_at": DateTime::now() } },
+            None,
         )
+        .await
         .unwrap();
 
-        send_verify_email(
-            &config.google_account_email,
-            &config.google_account_password,
-            &config.issuer,
-            String::from("/verify-email"),
-            code,
-            &sign_up.email,
-        );
+    send_verify_code(config, &sign_up.email);
 
-        // Response Ok.
-        Ok(Response::new(Code::Ok, None))
-    } else {
-        // Response email is already registered.
-        Err(Conflict(Some(Response::new(
-            Code::SignUpEmailAlreadyRegistered,
+    // Response Ok.
+    Ok(Response::new(Code::Ok, None))
+}
+
+/// # Resend the verification email API
+/// The email can be resent once a minute.
+/// ## Request
+/// - Path `/user/verify-email/resend`
+/// - Method: `POST`
+/// - [X] Authorization
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::AuthError]
+///     - [Code::LoginUserNotFoundError]
+///     - [Code::EmailAlreadyVerified]
+///     - [Code::TooManyRequests] - The last email was sent less than a minute ago.
+/// ## Curl Example
+/// ```bash
+/// curl -X POST -H "Authorization: Bearer {Token}" http://<host>/user/verify-email/resend
+/// ```
+#[post("/verify-email/resend")]
+async fn resend_verify_email(
+    login_user_data: Result<LoginUserData, AuthError>,
+    db: &State<Database>,
+    config: &State<Config>,
+) -> Result<Json<Response<String>>, (Status, Json<Response<String>>)> {
+    let find_user = find_login_user(login_user_data, db).await?;
+
+    if find_user.verified_email {
+        return Err((
+            Status::BadRequest,
+            Response::new(Code::EmailAlreadyVerified, None),
+        ));
+    }
+
+    let now = DateTime::now();
+    let last_sent_before =
+        DateTime::from_millis(now.timestamp_millis() - VERIFY_EMAIL_RESEND_INTERVAL * 1000);
+
+    // Update the sent time only if the interval has passed, so the concurrent requests send one email.
+    let result = db
+        .user
+        .as_ref()
+        .unwrap()
+        .update_one(
+            doc! {
+                "_id": find_user._id,
+                "$or": [
+                    { "verify_email_sent_at": null },
+                    { "verify_email_sent_at": { "$lte": last_sent_before } }
+                ]
+            },
+            doc! { "$set": { "verify_email_sent_at": now } },
             None,
-        ))))
+        )
+        .await
+        .unwrap();
+
+    if result.modified_count == 0 {
+        return Err((
+            Status::TooManyRequests,
+            Response::new(Code::TooManyRequests, None),
+        ));
     }
+
+    send_verify_code(config, &find_user.email);
+
+    // Response Ok.
+    Ok(Response::new(Code::Ok, None))
+}
+
+/// Send the verification email with a new code
+fn send_verify_code(config: &Config, email: &str) {
+    // The verify email code.
+    let code = create_jwt_token(
+        config.private_key.as_bytes(),
+        VerifyEmailClaims {
+            exp: create_exp(VERIFY_EMAIL_EXP),
+            purpose: TokenPurpose::VerifyEmail,
+            email: email.to_string(),
+        },
+    )
+    .unwrap();
+
+    send_verify_email(
+        &config.google_account_email,
+        &config.google_account_password,
+        &config.issuer,
+        String::from("/verify-email"),
+        code,
+        email,
+    );
 }
 
 /// # Get login user info
@@ -189,7 +950,6 @@ async fn sign_up(
 async fn get_user_info(
     login_user_data: Result<LoginUserData, AuthError>,
     db: &State<Database>,
-    request_ip: RequestIp,
 ) -> Result<Json<Response<UserInfo>>, AuthError> {
     // Check the user is logged in.
     let login_user_data = match login_user_data {
@@ -197,37 +957,21 @@ async fn get_user_info(
         Err(err) => return Err(err),
     };
 
-    let mut option = FindOneAndUpdateOptions::default();
-    option.upsert = Some(true);
-
     let find_user_data = db
         .user
         .as_ref()
         .unwrap()
-        .find_one_and_update(
+        .find_one(
             doc! {
                 "_id": ObjectId::parse_str(login_user_data.id).unwrap()
             },
-            doc! {
-                "$addToSet":{
-                    "login_ips": &request_ip.0
-                }
-            },
-            option,
+            None,
         )
         .await
         .unwrap();
 
     if let Some(user_info) = find_user_data {
-        Ok(Response::new(
-            Code::Ok,
-            Some(UserInfo {
-                username: user_info.username,
-                email: user_info.email,
-                modes: user_info.modes,
-                connects: user_info.connects,
-            }),
-        ))
+        Ok(Response::new(Code::Ok, Some(UserInfo::from(user_info))))
     } else {
         Err(Unauthorized(Some(Response::new(
             Code::LoginUserNotFoundError,
@@ -237,6 +981,7 @@ async fn get_user_info(
 }
 
 /// # Edit user info
+/// The modes are only chosen for the interface, the permissions are granted by the roles.
 /// ## Request
 /// - Path `/user/info`
 /// - Method: `PATCH`
@@ -247,6 +992,9 @@ async fn get_user_info(
 ///     - [Code::Ok]
 ///     - [Code::AuthError]
 ///     - [Code::LoginUserNotFoundError]
+///     - [Code::TeacherVerificationRequired]
+///     - [Code::AccountSuspended]
+///     - [Code::EditUserFailed] - The modes are changed by another request at the same time.
 /// - Content
 ///     - [UserInfo]
 /// ## Curl Example
@@ -258,33 +1006,49 @@ async fn edit_user_info(
     edit_user_data: Form<EditUserData>,
     login_user_data: Result<LoginUserData, AuthError>,
     db: &State<Database>,
-    request_ip: RequestIp,
-) -> Result<Json<Response<UserInfo>>, AuthError> {
-    // Check the user is logged in.
-    let login_user_data = match login_user_data {
-        Ok(login_user_data) => login_user_data,
-        Err(err) => return Err(err),
-    };
+) -> Result<Json<Response<UserInfo>>, (Status, Json<Response<String>>)> {
+    // The modes of the token may be changed by the admins or the teacher verification.
+    let find_user = find_login_user(login_user_data, db).await?;
 
-    let mut modes: Vec<UserMode> = login_user_data.modes;
+    if find_user.suspended_at.is_some() {
+        return Err((
+            Status::Forbidden,
+            Response::new(Code::AccountSuspended, None),
+        ));
+    }
+
+    let mut modes: Vec<UserMode> = find_user.modes.clone();
 
     if edit_user_data.is_student.is_some() {
         if edit_user_data.is_student.unwrap() {
-            modes.push(UserMode::Student);
+            if !modes.contains(&UserMode::Student) {
+                modes.push(UserMode::Student);
+            }
         } else {
             modes.retain(|x| *x != UserMode::Student);
         }
     }
     if edit_user_data.is_teacher.is_some() {
         if edit_user_data.is_teacher.unwrap() {
-            modes.push(UserMode::Teacher);
+            // The teacher mode requires the teacher role of the teacher verification.
+            if !roles_include(&find_user.roles, Role::Teacher) {
+                return Err((
+                    Status::Forbidden,
+                    Response::new(Code::TeacherVerificationRequired, None),
+                ));
+            }
+            if !modes.contains(&UserMode::Teacher) {
+                modes.push(UserMode::Teacher);
+            }
         } else {
             modes.retain(|x| *x != UserMode::Teacher);
         }
     }
     if edit_user_data.is_parents.is_some() {
         if edit_user_data.is_parents.unwrap() {
-            modes.push(UserMode::Parents);
+            if !modes.contains(&UserMode::Parents) {
+                modes.push(UserMode::Parents);
+            }
         } else {
             modes.retain(|x| *x != UserMode::Parents);
         }
@@ -293,11 +1057,11 @@ async fn edit_user_info(
     let username = if let Some(username) = &edit_user_data.username {
         username
     } else {
-        &login_user_data.username
+        &find_user.username
     };
 
     let mut option = FindOneAndUpdateOptions::default();
-    option.upsert = Some(true);
+    option.return_document = Some(ReturnDocument::After);
 
     let update_user_data = db
         .user
@@ -305,15 +1069,14 @@ async fn edit_user_info(
         .unwrap()
         .find_one_and_update(
             doc! {
-                "_id": ObjectId::parse_str(login_user_data.id).unwrap()
+                "_id": find_user._id,
+                // The modes are not changed since the user is loaded.
+                "modes": bson::to_bson(&find_user.modes).unwrap()
             },
             doc! {
-                "$setOnInsert": {
+                "$set": {
                    "username": username,
                    "modes": bson::to_bson(&modes).unwrap()
-                },
-                "$addToSet": {
-                    "login_ips": &request_ip.0,
                 }
             },
             option,
@@ -322,20 +1085,12 @@ async fn edit_user_info(
         .unwrap();
 
     if let Some(user_info) = update_user_data {
-        Ok(Response::new(
-            Code::Ok,
-            Some(UserInfo {
-                username: user_info.username,
-                email: user_info.email,
-                modes: user_info.modes,
-                connects: user_info.connects,
-            }),
-        ))
+        Ok(Response::new(Code::Ok, Some(UserInfo::from(user_info))))
     } else {
-        Err(Unauthorized(Some(Response::new(
-            Code::EditUserFailed,
-            None,
-        ))))
+        Err((
+            Status::Unauthorized,
+            Response::new(Code::EditUserFailed, None),
+        ))
     }
 }
 
@@ -344,7 +1099,21 @@ pub fn stage() -> AdHoc {
     AdHoc::on_ignite("load api stage", |rocket| async {
         rocket.mount(
             "/user",
-            routes![login, sign_up, get_user_info, edit_user_info],
+            routes![
+                login,
+                refresh_token,
+                logout,
+                logout_all,
+                forgot_password,
+                reset_password,
+                change_password,
+                change_email,
+                confirm_change_email,
+                sign_up,
+                resend_verify_email,
+                get_user_info,
+                edit_user_info
+            ],
         )
     })
 }
@@ -0,0 +1,533 @@
+use crate::apis::authentication::data::{OAuthProviders, RequestClient};
+use crate::apis::authentication::util::{
+    add_connect, create_login_token, create_oauth_url, find_login_user, get_oauth_account_info,
+    is_connect_linked, record_login_event, verify_oauth_state,
+};
+use crate::apis::user::lockout::{
+    is_login_locked, record_login_failure, reset_login_failures, send_unlock_code,
+};
+use crate::data::auth_data::{
+    AuthError, AuthUrl, Claims, ConfirmConnectData, ConnectConfirmCodeData, ConnectData,
+    LinkClaims, LinkTokenData, LoginToken, LoginUserData, CONNECT_CONFIRM_EXP,
+};
+use crate::data::code::Code;
+use crate::data::response::Response;
+use crate::data::user::ConnectInfo;
+use crate::Config;
+use database::model::auth::login_event::LoginMethod;
+use database::model::auth::user::{ConnectAccount, User};
+use database::mongodb::bson::{self, oid::ObjectId, DateTime};
+use database::{doc, Database};
+use rocket::fairing::AdHoc;
+use rocket::form::Form;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::State;
+use util::bcrypt::verify_password;
+use util::email::{send_connect_confirm_email, ConnectConfirmClaims};
+use util::jwt::{create_jwt_token, verify_token, TokenPurpose};
+use util::util::create_exp;
+
+type ConnectError = (Status, Json<Response<String>>);
+
+fn provider_not_found() -> ConnectError {
+    (Status::NotFound, Response::new(Code::NotFound, None))
+}
+
+fn link_token_error() -> ConnectError {
+    (
+        Status::Unauthorized,
+        Response::new(Code::LinkTokenError, None),
+    )
+}
+
+/// Find the user of the link token
+async fn find_link_user(
+    link_token: &str,
+    db: &Database,
+    config: &Config,
+) -> Result<(User, LinkClaims), ConnectError> {
+    let claims = verify_token::<LinkClaims>(link_token.to_string(), config.public_key.as_bytes())
+        .map_err(|_| link_token_error())?
+        .claims;
+
+    let find_user = db
+        .user
+        .as_ref()
+        .unwrap()
+        .find_one(
+            doc! {
+                "_id": ObjectId::parse_str(&claims.id).map_err(|_| link_token_error())?
+            },
+            None,
+        )
+        .await
+        .unwrap()
+        .ok_or_else(link_token_error)?;
+
+    Ok((find_user, claims))
+}
+
+/// # Get OAuth url to link a account
+/// ## Request
+/// - Path `/user/connects/<provider>/url`
+///     - `provider` - The OAuth provider, see `/authentication/<provider>/url`
+/// - Method: `GET`
+/// - Parameters
+///     - `redirect_uri`
+/// - [X] Authorization
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::NotFound] - The provider is not found.
+///     - [Code::AuthError]
+///     - [Code::LoginUserNotFoundError]
+/// - Content
+///     - [AuthUrl] - A OAuth url, the `state` can only be used by the login user
+/// ## Curl Example
+/// ```bash
+/// curl -X GET -H "Authorization: Bearer {Token}" http://<host>/user/connects/google/url?redirect_uri={redirect_uri}
+/// ```
+#[get("/connects/<provider>/url?<redirect_uri>")]
+async fn connect_url(
+    provider: &str,
+    redirect_uri: String,
+    login_user_data: Result<LoginUserData, AuthError>,
+    db: &State<Database>,
+    config: &State<Config>,
+    oauth_providers: &State<OAuthProviders>,
+) -> Result<Json<Response<AuthUrl>>, ConnectError> {
+    let find_user = find_login_user(login_user_data, db).await?;
+
+    let oauth = oauth_providers
+        .get(provider, redirect_uri)
+        .ok_or_else(provider_not_found)?;
+
+    Ok(Response::new(
+        Code::Ok,
+        Some(AuthUrl {
+            url: create_oauth_url(&oauth, &config.private_key, Some(find_user._id.to_string())),
+        }),
+    ))
+}
+
+/// # Link a account
+/// Link the account of the provider to the login user, each provider can be linked once.
+/// ## Request
+/// - Path `/user/connects/<provider>`
+///     - `provider` - The OAuth provider, see `/authentication/<provider>/url`
+/// - Method: `POST`
+/// - FromData [ConnectData]
+/// - [X] Authorization
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::NotFound] - The provider is not found.
+///     - [Code::AuthError]
+///     - [Code::LoginUserNotFoundError]
+///     - [Code::OAuthStateError]
+///     - [Code::OAuthCodeError]
+///     - [Code::OAuthGetUserInfoError]
+///     - [Code::ConnectAlreadyLinked]
+/// - Content
+///     - [ConnectInfo]
+/// ## Curl Example
+/// ```bash
+/// curl -X POST -H "Authorization: Bearer {Token}" -F code={code} -F state={state} http://<host>/user/connects/google
+/// ```
+#[post("/connects/<provider>", data = "<connect_data>")]
+async fn link_connect(
+    provider: &str,
+    connect_data: Form<ConnectData>,
+    login_user_data: Result<LoginUserData, AuthError>,
+    db: &State<Database>,
+    config: &State<Config>,
+    oauth_providers: &State<OAuthProviders>,
+) -> Result<Json<Response<ConnectInfo>>, ConnectError> {
+    let find_user = find_login_user(login_user_data, db).await?;
+
+    let mut oauth = oauth_providers
+        .get(provider, String::new())
+        .ok_or_else(provider_not_found)?;
+    let account_type = oauth.provider.account_type();
+
+    let state_claims = verify_oauth_state(
+        &connect_data.state,
+        account_type.clone(),
+        Some(&find_user._id.to_string()),
+        &config.public_key,
+    )?;
+    // Use the same redirect uri as the OAuth url
+    oauth.redirect_uri = state_claims.redirect_uri;
+
+    let account_info = get_oauth_account_info(
+        &oauth,
+        connect_data.code.clone(),
+        &connect_data.state,
+        &config.private_key,
+    )
+    .await?;
+
+    let already_linked = || {
+        (
+            Status::Conflict,
+            Response::new(Code::ConnectAlreadyLinked, None),
+        )
+    };
+
+    if find_user
+        .connects
+        .iter()
+        .any(|connect| connect.account_type == account_type)
+    {
+        return Err(already_linked());
+    }
+
+    let connect = ConnectAccount {
+        account_type,
+        subject: Some(account_info.id),
+        name: account_info.name,
+        email: account_info.email,
+        avatar: Some(account_info.picture).filter(|picture| !picture.is_empty()),
+        last_login: None,
+        school_id: account_info.school_id,
+        grade: account_info.grade,
+    };
+
+    // The account of the provider can only be linked to one user.
+    if is_connect_linked(db.user.as_ref().unwrap(), &connect)
+        .await
+        .unwrap()
+    {
+        return Err(already_linked());
+    }
+
+    db.user
+        .as_ref()
+        .unwrap()
+        .update_one(
+            doc! { "_id": find_user._id },
+            doc! {
+                "$push": {
+                    "connects": bson::to_bson(&connect).unwrap()
+                }
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    Ok(Response::new(Code::Ok, Some(ConnectInfo::from(connect))))
+}
+
+/// # Unlink a account
+/// The last login method (password, passkey or connected account) of the user can't be removed.
+/// ## Request
+/// - Path `/user/connects/<provider>`
+///     - `provider` - The OAuth provider, see `/authentication/<provider>/url`
+/// - Method: `DELETE`
+/// - [X] Authorization
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::NotFound] - The provider is not found.
+///     - [Code::AuthError]
+///     - [Code::LoginUserNotFoundError]
+///     - [Code::ConnectNotFound]
+///     - [Code::LastLoginMethod]
+/// ## Curl Example
+/// ```bash
+/// curl -X DELETE -H "Authorization: Bearer {Token}" http://<host>/user/connects/google
+/// ```
+#[delete("/connects/<provider>")]
+async fn unlink_connect(
+    provider: &str,
+    login_user_data: Result<LoginUserData, AuthError>,
+    db: &State<Database>,
+    oauth_providers: &State<OAuthProviders>,
+) -> Result<Json<Response<String>>, ConnectError> {
+    let find_user = find_login_user(login_user_data, db).await?;
+
+    let account_type = oauth_providers
+        .get(provider, String::new())
+        .ok_or_else(provider_not_found)?
+        .provider
+        .account_type();
+
+    let (removed, remaining): (Vec<_>, Vec<_>) = find_user
+        .connects
+        .iter()
+        .partition(|connect| connect.account_type == account_type);
+
+    if removed.is_empty() {
+        return Err((Status::NotFound, Response::new(Code::ConnectNotFound, None)));
+    }
+
+    if remaining.is_empty() && find_user.password_hash.is_none() && find_user.passkeys.is_empty() {
+        return Err((Status::Conflict, Response::new(Code::LastLoginMethod, None)));
+    }
+
+    db.user
+        .as_ref()
+        .unwrap()
+        .update_one(
+            doc! { "_id": find_user._id },
+            doc! {
+                "$pull": {
+                    "connects": {
+                        "account_type": bson::to_bson(&account_type).unwrap()
+                    }
+                }
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    // Response Ok.
+    Ok(Response::new(Code::Ok, None))
+}
+
+/// # Confirm linking a account with the password
+/// Link the account from the [LinkToken](crate::data::auth_data::LinkToken) of the OAuth login
+/// to the existing user, and login.
+/// ## Request
+/// - Path `/user/connects/confirm`
+/// - Method: `POST`
+/// - FromData [ConfirmConnectData]
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::LinkTokenError]
+///     - [Code::LoginPasswordError]
+///     - [Code::LoginLocked] - Too many failed logins of the email or the ip.
+///     - [Code::ConnectAlreadyLinked]
+///     - [Code::MfaRequired] - Two-factor authentication is required, see `/user/login/mfa`.
+///     - [Code::AccountPendingDeletion]
+///     - [Code::AccountSuspended]
+/// - Content
+///     - [Token](crate::data::auth_data::Token) - A login token.
+///     - [MfaToken](crate::data::auth_data::MfaToken) - If the code is [Code::MfaRequired].
+/// ## Curl Example
+/// ```bash
+/// curl -X POST -F link_token={link_token} -F password=password http://<host>/user/connects/confirm
+/// ```
+#[post("/connects/confirm", data = "<confirm_connect_data>")]
+async fn confirm_connect(
+    confirm_connect_data: Form<ConfirmConnectData>,
+    db: &State<Database>,
+    config: &State<Config>,
+    request_client: RequestClient,
+) -> Result<Json<Response<LoginToken>>, ConnectError> {
+    let (find_user, claims) = find_link_user(&confirm_connect_data.link_token, db, config).await?;
+
+    // The password is locked out like `/user/login`, by the email of the existing user.
+    if is_login_locked(db, &find_user.email, &request_client.ip)
+        .await
+        .unwrap()
+    {
+        return Err((
+            Status::TooManyRequests,
+            Response::new(Code::LoginLocked, None),
+        ));
+    }
+
+    let verified = match &find_user.password_hash {
+        Some(password_hash) => {
+            verify_password(password_hash.clone(), &confirm_connect_data.password).unwrap()
+        }
+        None => false,
+    };
+
+    if !verified {
+        record_login_event(
+            db,
+            find_user._id,
+            LoginMethod::Password,
+            false,
+            &request_client,
+        )
+        .await
+        .unwrap();
+
+        let locked = record_login_failure(db, &find_user.email, &request_client.ip)
+            .await
+            .unwrap();
+
+        if locked {
+            send_unlock_code(config, &find_user.email);
+        }
+
+        return Err((
+            Status::Unauthorized,
+            Response::new(Code::LoginPasswordError, None),
+        ));
+    }
+
+    reset_login_failures(db, &find_user.email).await.unwrap();
+
+    let user = db.user.as_ref().unwrap();
+
+    if is_connect_linked(user, &claims.connect).await.unwrap() {
+        return Err((
+            Status::Conflict,
+            Response::new(Code::ConnectAlreadyLinked, None),
+        ));
+    }
+
+    let account_type = claims.connect.account_type.clone();
+    add_connect(
+        user,
+        find_user._id,
+        &ConnectAccount {
+            last_login: Some(DateTime::now()),
+            ..claims.connect
+        },
+    )
+    .await
+    .unwrap();
+
+    let claims = Claims::new(
+        find_user._id.to_string(),
+        find_user.username.clone(),
+        find_user.verified_email,
+        find_user.modes.clone(),
+    );
+
+    // Response token.
+    create_login_token(
+        db,
+        &config.private_key,
+        &find_user,
+        claims,
+        LoginMethod::Connect(account_type),
+        &request_client,
+    )
+    .await
+}
+
+/// # Send the email to confirm linking a account
+/// Send a code to the email of the existing user, see `/user/connects/confirm/code`.
+/// ## Request
+/// - Path `/user/connects/confirm/email`
+/// - Method: `POST`
+/// - FromData [LinkTokenData]
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::LinkTokenError]
+/// ## Curl Example
+/// ```bash
+/// curl -X POST -F link_token={link_token} http://<host>/user/connects/confirm/email
+/// ```
+#[post("/connects/confirm/email", data = "<link_token_data>")]
+async fn send_confirm_connect_email(
+    link_token_data: Form<LinkTokenData>,
+    db: &State<Database>,
+    config: &State<Config>,
+) -> Result<Json<Response<String>>, ConnectError> {
+    let (find_user, claims) = find_link_user(&link_token_data.link_token, db, config).await?;
+
+    let connect_email = claims.connect.email.clone();
+    let code = create_jwt_token(
+        config.private_key.as_bytes(),
+        ConnectConfirmClaims {
+            exp: create_exp(CONNECT_CONFIRM_EXP),
+            purpose: TokenPurpose::ConnectConfirm,
+            id: claims.id,
+            confirm_email: find_user.email.clone(),
+            connect: claims.connect,
+        },
+    )
+    .unwrap();
+
+    send_connect_confirm_email(
+        &config.google_account_email,
+        &config.google_account_password,
+        &config.connect_confirm_url,
+        code,
+        &connect_email,
+        &find_user.email,
+    );
+
+    // Response Ok.
+    Ok(Response::new(Code::Ok, None))
+}
+
+/// # Confirm linking a account with the email code
+/// Link the account to the user and verify the email of the user.
+/// ## Request
+/// - Path `/user/connects/confirm/code`
+/// - Method: `POST`
+/// - FromData [ConnectConfirmCodeData]
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::LinkTokenError]
+///     - [Code::ConnectAlreadyLinked]
+/// ## Curl Example
+/// ```bash
+/// curl -X POST -F code={code} http://<host>/user/connects/confirm/code
+/// ```
+#[post("/connects/confirm/code", data = "<confirm_code_data>")]
+async fn confirm_connect_code(
+    confirm_code_data: Form<ConnectConfirmCodeData>,
+    db: &State<Database>,
+    config: &State<Config>,
+) -> Result<Json<Response<String>>, ConnectError> {
+    let claims = verify_token::<ConnectConfirmClaims>(
+        confirm_code_data.code.clone(),
+        config.public_key.as_bytes(),
+    )
+    .map_err(|_| link_token_error())?
+    .claims;
+
+    let user = db.user.as_ref().unwrap();
+    let user_id = ObjectId::parse_str(&claims.id).map_err(|_| link_token_error())?;
+
+    // The email of the user may be changed after the code is sent.
+    let find_user = user
+        .find_one(doc! {"_id": user_id}, None)
+        .await
+        .unwrap()
+        .filter(|find_user| find_user.email == claims.confirm_email)
+        .ok_or_else(link_token_error)?;
+
+    if is_connect_linked(user, &claims.connect).await.unwrap() {
+        return Err((
+            Status::Conflict,
+            Response::new(Code::ConnectAlreadyLinked, None),
+        ));
+    }
+
+    add_connect(user, find_user._id, &claims.connect)
+        .await
+        .unwrap();
+    user.update_one(
+        doc! {"_id": find_user._id},
+        doc! {"$set": {"verified_email": true}},
+        None,
+    )
+    .await
+    .unwrap();
+
+    // Response Ok.
+    Ok(Response::new(Code::Ok, None))
+}
+
+#[doc(hidden)]
+pub fn stage() -> AdHoc {
+    AdHoc::on_ignite("load connect stage", |rocket| async {
+        rocket.mount(
+            "/user",
+            routes![
+                connect_url,
+                link_connect,
+                unlink_connect,
+                confirm_connect,
+                send_confirm_connect_email,
+                confirm_connect_code
+            ],
+        )
+    })
+}
@@ -0,0 +1,241 @@
+use std::time::Duration;
+
+use crate::apis::authentication::util::{
+    find_login_user, revoke_all_sessions, verify_reauthentication,
+};
+use crate::data::auth_data::{AuthError, CancelDeletionData, DeleteUserData, LoginUserData};
+use crate::data::code::Code;
+use crate::data::response::Response;
+use crate::Config;
+use database::mongodb::bson::{oid::ObjectId, DateTime};
+use database::{doc, Database, Error};
+use rocket::fairing::AdHoc;
+use rocket::form::Form;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::State;
+use util::email::{send_account_deletion_email, CancelDeletionClaims};
+use util::jwt::{create_jwt_token, verify_token, TokenPurpose};
+
+/// The interval of purging the users after the grace period (1 hour)
+const PURGE_INTERVAL: u64 = 60 * 60;
+
+type DeleteError = (Status, Json<Response<String>>);
+
+/// # Delete the login user
+/// The user is scheduled for deletion and logged out from all sessions,
+/// and purged with all owned data after the grace period (`account_deletion_grace_days` of the config).
+/// A link to cancel the deletion is sent to the email, the user can't login before it is canceled.
+/// The current password is required if the user has a password,
+/// otherwise the user must have logged in within 10 minutes.
+/// ## Request
+/// - Path `/user`
+/// - Method: `DELETE`
+/// - FromData [DeleteUserData]
+/// - [X] Authorization
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::AuthError]
+///     - [Code::LoginUserNotFoundError]
+///     - [Code::LoginPasswordError] - Input password error.
+///     - [Code::ReauthenticationRequired]
+/// ## Curl Example
+/// ```bash
+/// curl -X DELETE -H "Authorization: Bearer {Token}" -F password=lipoic2022 http://<host>/user
+/// ```
+#[delete("/", data = "<delete_user_data>")]
+async fn delete_user(
+    delete_user_data: Form<DeleteUserData>,
+    login_user_data: Result<LoginUserData, AuthError>,
+    db: &State<Database>,
+    config: &State<Config>,
+) -> Result<Json<Response<String>>, DeleteError> {
+    let auth_time = login_user_data
+        .as_ref()
+        .map(|data| data.auth_time)
+        .unwrap_or_default();
+    let find_user = find_login_user(login_user_data, db).await?;
+
+    verify_reauthentication(&find_user, &delete_user_data.password, auth_time)?;
+
+    let delete_at = DateTime::from_millis(
+        DateTime::now().timestamp_millis()
+            + config.account_deletion_grace_days * 24 * 60 * 60 * 1000,
+    );
+
+    db.user
+        .as_ref()
+        .unwrap()
+        .update_one(
+            doc! { "_id": find_user._id },
+            doc! { "$set": { "delete_at": delete_at } },
+            None,
+        )
+        .await
+        .unwrap();
+
+    revoke_all_sessions(db, find_user._id).await.unwrap();
+
+    // The cancel deletion code, valid until the user is purged.
+    let code = create_jwt_token(
+        config.private_key.as_bytes(),
+        CancelDeletionClaims {
+            exp: (delete_at.timestamp_millis() / 1000) as usize,
+            purpose: TokenPurpose::CancelDeletion,
+            id: find_user._id.to_string(),
+            delete_at: delete_at.timestamp_millis(),
+        },
+    )
+    .unwrap();
+
+    send_account_deletion_email(
+        &config.google_account_email,
+        &config.google_account_password,
+        &config.cancel_deletion_url,
+        code,
+        config.account_deletion_grace_days,
+        &find_user.email,
+    );
+
+    // Response Ok.
+    Ok(Response::new(Code::Ok, None))
+}
+
+/// # Cancel the deletion of the user
+/// Cancel the deletion by the code from the deletion email, the user can login again.
+/// ## Request
+/// - Path `/user/delete/cancel`
+/// - Method: `POST`
+/// - FromData [CancelDeletionData]
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::CancelDeletionError]
+/// ## Curl Example
+/// ```bash
+/// curl -X POST -F code={code} http://<host>/user/delete/cancel
+/// ```
+#[post("/delete/cancel", data = "<cancel_deletion_data>")]
+async fn cancel_deletion(
+    cancel_deletion_data: Form<CancelDeletionData>,
+    db: &State<Database>,
+    config: &State<Config>,
+) -> Result<Json<Response<String>>, DeleteError> {
+    let cancel_deletion_error = || {
+        (
+            Status::Unauthorized,
+            Response::new(Code::CancelDeletionError, None),
+        )
+    };
+
+    let claims = verify_token::<CancelDeletionClaims>(
+        cancel_deletion_data.code.clone(),
+        config.public_key.as_bytes(),
+    )
+    .map_err(|_| cancel_deletion_error())?
+    .claims;
+
+    // The code is only valid for the same deletion request.
+    let result = db
+        .user
+        .as_ref()
+        .unwrap()
+        .update_one(
+            doc! {
+                "_id": ObjectId::parse_str(&claims.id).map_err(|_| cancel_deletion_error())?,
+                "delete_at": DateTime::from_millis(claims.delete_at)
+            },
+            doc! { "$unset": { "delete_at": "" } },
+            None,
+        )
+        .await
+        .unwrap();
+
+    if result.modified_count == 0 {
+        return Err(cancel_deletion_error());
+    }
+
+    // Response Ok.
+    Ok(Response::new(Code::Ok, None))
+}
+
+/// Purge the users after the grace period, with the tokens, the sessions, the data exports,
+/// the login events and the teacher verification requests of the users
+async fn purge_deleted_users(db: &Database) -> Result<(), Error> {
+    let user = db.user.as_ref().unwrap();
+    let mut cursor = user
+        .find(doc! { "delete_at": { "$lte": DateTime::now() } }, None)
+        .await?;
+
+    while cursor.advance().await? {
+        let user_id = cursor.deserialize_current()?._id;
+
+        db.refresh_token
+            .as_ref()
+            .unwrap()
+            .delete_many(doc! { "user_id": user_id }, None)
+            .await?;
+        db.revoked_token
+            .as_ref()
+            .unwrap()
+            .delete_many(doc! { "user_id": user_id }, None)
+            .await?;
+        db.data_export
+            .as_ref()
+            .unwrap()
+            .delete_many(doc! { "user_id": user_id }, None)
+            .await?;
+        db.login_events
+            .as_ref()
+            .unwrap()
+            .delete_many(doc! { "user_id": user_id }, None)
+            .await?;
+        db.session
+            .as_ref()
+            .unwrap()
+            .delete_many(doc! { "user_id": user_id }, None)
+            .await?;
+        db.teacher_verification
+            .as_ref()
+            .unwrap()
+            .delete_many(doc! { "user_id": user_id }, None)
+            .await?;
+        user.delete_one(doc! { "_id": user_id }, None).await?;
+
+        info!("Purged the deleted user {}", user_id);
+    }
+
+    Ok(())
+}
+
+#[doc(hidden)]
+pub fn stage() -> AdHoc {
+    AdHoc::on_ignite("load delete stage", |rocket| async {
+        rocket
+            .mount("/user", routes![delete_user, cancel_deletion])
+            .attach(AdHoc::on_liftoff("purge deleted users", |rocket| {
+                Box::pin(async move {
+                    let db = rocket.state::<Database>().unwrap().clone();
+
+                    // The database is not connected in the tests.
+                    if db.user.is_none() {
+                        return;
+                    }
+
+                    rocket::tokio::spawn(async move {
+                        let mut interval =
+                            rocket::tokio::time::interval(Duration::from_secs(PURGE_INTERVAL));
+
+                        loop {
+                            interval.tick().await;
+
+                            if let Err(error) = purge_deleted_users(&db).await {
+                                warn!("Failed to purge the deleted users: {:?}", error);
+                            }
+                        }
+                    });
+                })
+            }))
+    })
+}
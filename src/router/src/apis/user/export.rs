@@ -0,0 +1,224 @@
+use crate::apis::authentication::util::find_login_user;
+use crate::data::auth_data::{AuthError, LoginUserData};
+use crate::data::code::Code;
+use crate::data::response::Response;
+use crate::data::user::UserExport;
+use crate::Config;
+use database::model::auth::user::User;
+use database::model::export::DataExport;
+use database::mongodb::bson::{oid::ObjectId, DateTime};
+use database::{doc, Database, Error};
+use rocket::fairing::AdHoc;
+use rocket::http::{Header, Status};
+use rocket::serde::json::{serde_json, Json};
+use rocket::State;
+use util::email::send_data_export_email;
+use util::util::{create_random_token, hash_token};
+
+/// The download link expiration time (24 hours)
+const DATA_EXPORT_EXP: i64 = 60 * 60 * 24;
+/// The minimum interval between two data exports (1 hour)
+const DATA_EXPORT_INTERVAL: i64 = 60 * 60;
+
+type ExportError = (Status, Json<Response<String>>);
+
+/// The JSON archive of the user data
+#[derive(Responder)]
+#[response(content_type = "json")]
+pub struct ExportFile(String, Header<'static>);
+
+/// # Export the personal data of the login user
+/// The data is exported in the background, and a download link is sent to the email.
+/// The data can be exported once an hour.
+/// ## Request
+/// - Path `/user/export`
+/// - Method: `POST`
+/// - [X] Authorization
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::AuthError]
+///     - [Code::LoginUserNotFoundError]
+///     - [Code::TooManyRequests] - The last export was requested less than an hour ago.
+/// ## Curl Example
+/// ```bash
+/// curl -X POST -H "Authorization: Bearer {Token}" http://<host>/user/export
+/// ```
+#[post("/export")]
+async fn export_data(
+    login_user_data: Result<LoginUserData, AuthError>,
+    db: &State<Database>,
+    config: &State<Config>,
+) -> Result<Json<Response<String>>, ExportError> {
+    let find_user = find_login_user(login_user_data, db).await?;
+
+    let now = DateTime::now();
+    let last_exported_before =
+        DateTime::from_millis(now.timestamp_millis() - DATA_EXPORT_INTERVAL * 1000);
+
+    // Update the export time only if the interval has passed, so the concurrent requests export once.
+    let result = db
+        .user
+        .as_ref()
+        .unwrap()
+        .update_one(
+            doc! {
+                "_id": find_user._id,
+                "$or": [
+                    { "data_exported_at": null },
+                    { "data_exported_at": { "$lte": last_exported_before } }
+                ]
+            },
+            doc! { "$set": { "data_exported_at": now } },
+            None,
+        )
+        .await
+        .unwrap();
+
+    if result.modified_count == 0 {
+        return Err((
+            Status::TooManyRequests,
+            Response::new(Code::TooManyRequests, None),
+        ));
+    }
+
+    let db = (*db).clone();
+    let download_url = format!("{}/user/export/download", config.issuer);
+    let email_username = config.google_account_email.clone();
+    let email_password = config.google_account_password.clone();
+
+    rocket::tokio::spawn(async move {
+        match create_data_export(&db, find_user).await {
+            Ok((email, token)) => send_data_export_email(
+                &email_username,
+                &email_password,
+                &format!("{}?token={}", download_url, token),
+                &email,
+            ),
+            Err(error) => warn!("Failed to export the user data: {:?}", error),
+        }
+    });
+
+    // Response Ok.
+    Ok(Response::new(Code::Ok, None))
+}
+
+/// Create the data export of the user
+///
+/// return the email of the user and the download token
+async fn create_data_export(db: &Database, user: User) -> Result<(String, String), Error> {
+    let user_id = user._id;
+    let email = user.email.clone();
+
+    let mut sessions = vec![];
+    let mut cursor = db
+        .session
+        .as_ref()
+        .unwrap()
+        .find(doc! { "user_id": user_id }, None)
+        .await?;
+    while cursor.advance().await? {
+        sessions.push(cursor.deserialize_current()?);
+    }
+
+    let mut login_events = vec![];
+    let mut cursor = db
+        .login_events
+        .as_ref()
+        .unwrap()
+        .find(doc! { "user_id": user_id }, None)
+        .await?;
+    while cursor.advance().await? {
+        login_events.push(cursor.deserialize_current()?);
+    }
+
+    let mut teacher_verifications = vec![];
+    let mut cursor = db
+        .teacher_verification
+        .as_ref()
+        .unwrap()
+        .find(doc! { "user_id": user_id }, None)
+        .await?;
+    while cursor.advance().await? {
+        teacher_verifications.push(cursor.deserialize_current()?);
+    }
+
+    let data = serde_json::to_string_pretty(&UserExport::new(
+        user,
+        sessions,
+        login_events,
+        teacher_verifications,
+    ))
+    .unwrap();
+
+    let token = create_random_token(64);
+    let now = DateTime::now();
+    db.data_export
+        .as_ref()
+        .unwrap()
+        .insert_one(
+            DataExport {
+                _id: ObjectId::new(),
+                user_id,
+                token_hash: hash_token(&token),
+                data,
+                created_at: now,
+                exp: DateTime::from_millis(now.timestamp_millis() + DATA_EXPORT_EXP * 1000),
+            },
+            None,
+        )
+        .await?;
+
+    Ok((email, token))
+}
+
+/// # Download the data export
+/// ## Request
+/// - Path `/user/export/download`
+/// - Method: `GET`
+/// - Parameters
+///     - `token` - The token from the data export email
+/// ## Response
+/// - A JSON file of [UserExport]
+/// - Code
+///     - [Code::NotFound] - The token is invalid or expired.
+/// ## Curl Example
+/// ```bash
+/// curl -X GET http://<host>/user/export/download?token={token}
+/// ```
+#[get("/export/download?<token>")]
+async fn download_data_export(
+    token: String,
+    db: &State<Database>,
+) -> Result<ExportFile, ExportError> {
+    let data_export = db
+        .data_export
+        .as_ref()
+        .unwrap()
+        .find_one(
+            doc! {
+                "token_hash": hash_token(&token),
+                // The expired document may not be removed yet.
+                "exp": { "$gt": DateTime::now() }
+            },
+            None,
+        )
+        .await
+        .unwrap()
+        .ok_or((Status::NotFound, Response::new(Code::NotFound, None)))?;
+
+    Ok(ExportFile(
+        data_export.data,
+        Header::new(
+            "Content-Disposition",
+            "attachment; filename=\"lipoic-data-export.json\"",
+        ),
+    ))
+}
+
+#[doc(hidden)]
+pub fn stage() -> AdHoc {
+    AdHoc::on_ignite("load export stage", |rocket| async {
+        rocket.mount("/user", routes![export_data, download_data_export])
+    })
+}
@@ -0,0 +1,198 @@
+use crate::data::auth_data::UnlockAccountData;
+use crate::data::code::Code;
+use crate::data::response::Response;
+use crate::Config;
+use database::mongodb::bson::DateTime;
+use database::mongodb::options::{FindOneAndUpdateOptions, ReturnDocument};
+use database::{doc, is_duplicate_key_error, Database, Error};
+use rocket::fairing::AdHoc;
+use rocket::form::Form;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::State;
+use util::email::{send_unlock_account_email, UnlockAccountClaims};
+use util::jwt::{create_jwt_token, verify_token, TokenPurpose};
+use util::util::{backoff_time, create_exp};
+
+/// The failed logins of a email before the email is locked
+const ACCOUNT_FAILURE_LIMIT: i64 = 5;
+/// The failed logins of a ip before the ip is locked
+const IP_FAILURE_LIMIT: i64 = 20;
+/// The first lockout time (1 minute), doubled by every failure after the limit
+const LOCKOUT_BASE: i64 = 60;
+/// The maximum lockout time (1 hour)
+const LOCKOUT_MAX: i64 = 60 * 60;
+/// The failures are forgotten a day after the last failure
+const FAILURE_EXP: i64 = 60 * 60 * 24;
+/// The unlock account code expiration time (1 hour)
+const UNLOCK_ACCOUNT_EXP: usize = 60 * 60;
+
+/// The key is case folded, so the lockout can't be bypassed by changing the case of the email
+fn email_key(email: &str) -> String {
+    format!("email:{}", email.to_lowercase())
+}
+
+fn ip_key(ip: &str) -> String {
+    format!("ip:{}", ip)
+}
+
+/// Check the login of the email or the ip is locked
+#[doc(hidden)]
+pub async fn is_login_locked(db: &Database, email: &str, ip: &str) -> Result<bool, Error> {
+    Ok(db
+        .login_failure
+        .as_ref()
+        .unwrap()
+        .find_one(
+            doc! {
+                "key": { "$in": [email_key(email), ip_key(ip)] },
+                "locked_until": { "$gt": DateTime::now() }
+            },
+            None,
+        )
+        .await?
+        .is_some())
+}
+
+/// Count a failed login of the email and the ip, and lock them with exponential backoff after the limits
+///
+/// return `true` if the email is just locked
+#[doc(hidden)]
+pub async fn record_login_failure(db: &Database, email: &str, ip: &str) -> Result<bool, Error> {
+    let login_failure = db.login_failure.as_ref().unwrap();
+    let now = DateTime::now().timestamp_millis();
+
+    let mut option = FindOneAndUpdateOptions::default();
+    option.upsert = Some(true);
+    option.return_document = Some(ReturnDocument::After);
+
+    let mut email_locked = false;
+
+    for (key, limit) in [
+        (email_key(email), ACCOUNT_FAILURE_LIMIT),
+        (ip_key(ip), IP_FAILURE_LIMIT),
+    ] {
+        let failure = loop {
+            match login_failure
+                .find_one_and_update(
+                    doc! { "key": &key },
+                    doc! {
+                        "$inc": { "count": 1_i64 },
+                        "$set": { "exp": DateTime::from_millis(now + FAILURE_EXP * 1000) },
+                        "$setOnInsert": { "locked_until": null }
+                    },
+                    option.clone(),
+                )
+                .await
+            {
+                // The concurrent first failures of the key insert the same key, retry to update it.
+                Err(err) if is_duplicate_key_error(&err) => continue,
+                result => break result?.unwrap(),
+            }
+        };
+
+        if failure.count >= limit {
+            let lockout = backoff_time(failure.count - limit, LOCKOUT_BASE, LOCKOUT_MAX);
+
+            login_failure
+                .update_one(
+                    doc! { "key": &key },
+                    doc! {
+                        "$set": {
+                            "locked_until": DateTime::from_millis(now + lockout * 1000)
+                        }
+                    },
+                    None,
+                )
+                .await?;
+
+            if failure.count == ACCOUNT_FAILURE_LIMIT && key.starts_with("email:") {
+                email_locked = true;
+            }
+        }
+    }
+
+    Ok(email_locked)
+}
+
+/// Forget the failed logins of the email after a successful login
+#[doc(hidden)]
+pub async fn reset_login_failures(db: &Database, email: &str) -> Result<(), Error> {
+    db.login_failure
+        .as_ref()
+        .unwrap()
+        .delete_one(doc! { "key": email_key(email) }, None)
+        .await?;
+
+    Ok(())
+}
+
+/// Send the email to unlock the login of the email
+#[doc(hidden)]
+pub fn send_unlock_code(config: &Config, email: &str) {
+    let code = create_jwt_token(
+        config.private_key.as_bytes(),
+        UnlockAccountClaims {
+            exp: create_exp(UNLOCK_ACCOUNT_EXP),
+            purpose: TokenPurpose::UnlockAccount,
+            unlock_email: email.to_string(),
+        },
+    )
+    .unwrap();
+
+    send_unlock_account_email(
+        &config.google_account_email,
+        &config.google_account_password,
+        &config.unlock_account_url,
+        code,
+        email,
+    );
+}
+
+/// # Unlock the account
+/// Unlock the login of the email by the code from the account locked email,
+/// the ips with too many failed logins are still locked.
+/// ## Request
+/// - Path `/user/login/unlock`
+/// - Method: `POST`
+/// - FromData [UnlockAccountData]
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::UnlockAccountError]
+/// ## Curl Example
+/// ```bash
+/// curl -X POST -F code={code} http://<host>/user/login/unlock
+/// ```
+#[post("/login/unlock", data = "<unlock_account_data>")]
+async fn unlock_account(
+    unlock_account_data: Form<UnlockAccountData>,
+    db: &State<Database>,
+    config: &State<Config>,
+) -> Result<Json<Response<String>>, (Status, Json<Response<String>>)> {
+    let claims = verify_token::<UnlockAccountClaims>(
+        unlock_account_data.code.clone(),
+        config.public_key.as_bytes(),
+    )
+    .map_err(|_| {
+        (
+            Status::Unauthorized,
+            Response::new(Code::UnlockAccountError, None),
+        )
+    })?
+    .claims;
+
+    reset_login_failures(db, &claims.unlock_email)
+        .await
+        .unwrap();
+
+    // Response Ok.
+    Ok(Response::new(Code::Ok, None))
+}
+
+#[doc(hidden)]
+pub fn stage() -> AdHoc {
+    AdHoc::on_ignite("load lockout stage", |rocket| async {
+        rocket.mount("/user", routes![unlock_account])
+    })
+}
@@ -0,0 +1,170 @@
+use crate::apis::authentication::data::RequestClient;
+use crate::apis::authentication::util::create_login_token;
+use crate::data::auth_data::{
+    Claims, LoginToken, MagicLinkData, MagicLinkLoginData, MAGIC_LINK_EXP,
+};
+use crate::data::code::Code;
+use crate::data::response::Response;
+use crate::Config;
+use database::model::auth::login_event::LoginMethod;
+use database::mongodb::options::{FindOneAndUpdateOptions, ReturnDocument};
+use database::{doc, Database};
+use rocket::fairing::AdHoc;
+use rocket::form::Form;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::State;
+use util::email::{send_magic_link_email, MagicLinkClaims};
+use util::jwt::{create_jwt_token, verify_token, TokenPurpose};
+use util::util::{create_exp, create_random_token, hash_token};
+
+type MagicLinkError = (Status, Json<Response<String>>);
+
+fn magic_link_error() -> MagicLinkError {
+    (
+        Status::Unauthorized,
+        Response::new(Code::MagicLinkError, None),
+    )
+}
+
+/// # Send a magic link
+/// Send a login link to the email if the email is registered, the link can only be used once and expires in 10 minutes.
+/// Only the last sent link is valid.
+/// ## Request
+/// - Path `/user/login/magic-link`
+/// - Method: `POST`
+/// - FromData [MagicLinkData]
+/// ## Response
+/// - Code
+///     - [Code::Ok] - Always responded, whether the email is registered or not.
+/// ## Curl Example
+/// ```bash
+/// curl -X POST -F email=aijdfajodwsdf@gmail.com http://<host>/user/login/magic-link
+/// ```
+#[post("/login/magic-link", data = "<magic_link_data>")]
+async fn send_magic_link(
+    magic_link_data: Form<MagicLinkData>,
+    db: &State<Database>,
+    config: &State<Config>,
+) -> Json<Response<String>> {
+    let nonce = create_random_token(32);
+
+    if let Some(user_data) = db
+        .user
+        .as_ref()
+        .unwrap()
+        .find_one_and_update(
+            doc! { "email": &magic_link_data.email },
+            doc! { "$set": { "magic_link_nonce": hash_token(&nonce) } },
+            None,
+        )
+        .await
+        .unwrap()
+    {
+        // The magic link code.
+        let code = create_jwt_token(
+            config.private_key.as_bytes(),
+            MagicLinkClaims {
+                exp: create_exp(MAGIC_LINK_EXP),
+                purpose: TokenPurpose::MagicLink,
+                email: user_data.email.clone(),
+                nonce,
+            },
+        )
+        .unwrap();
+
+        send_magic_link_email(
+            &config.google_account_email,
+            &config.google_account_password,
+            &config.magic_link_url,
+            code,
+            &user_data.email,
+        );
+    }
+
+    // Response Ok.
+    Response::new(Code::Ok, None)
+}
+
+/// # Login with a magic link
+/// The email of the user is verified by the magic link.
+/// ## Request
+/// - Path `/user/login/magic-link/callback`
+/// - Method: `POST`
+/// - FromData [MagicLinkLoginData]
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::MagicLinkError]
+///     - [Code::MfaRequired] - Two-factor authentication is required, see `/user/login/mfa`.
+///     - [Code::AccountPendingDeletion]
+///     - [Code::AccountSuspended]
+/// - Content
+///     - [Token](crate::data::auth_data::Token) - A login token.
+///     - [MfaToken](crate::data::auth_data::MfaToken) - If the code is [Code::MfaRequired].
+/// ## Curl Example
+/// ```bash
+/// curl -X POST -F code={code} http://<host>/user/login/magic-link/callback
+/// ```
+#[post("/login/magic-link/callback", data = "<magic_link_login_data>")]
+async fn magic_link_login(
+    magic_link_login_data: Form<MagicLinkLoginData>,
+    db: &State<Database>,
+    config: &State<Config>,
+    request_client: RequestClient,
+) -> Result<Json<Response<LoginToken>>, MagicLinkError> {
+    let claims = verify_token::<MagicLinkClaims>(
+        magic_link_login_data.code.clone(),
+        config.public_key.as_bytes(),
+    )
+    .map_err(|_| magic_link_error())?
+    .claims;
+
+    let mut option = FindOneAndUpdateOptions::default();
+    option.return_document = Some(ReturnDocument::After);
+
+    // Remove the nonce, so the link can't be used again.
+    let find_user = db
+        .user
+        .as_ref()
+        .unwrap()
+        .find_one_and_update(
+            doc! {
+                "email": &claims.email,
+                "magic_link_nonce": hash_token(&claims.nonce)
+            },
+            doc! {
+                "$set": { "verified_email": true },
+                "$unset": { "magic_link_nonce": "" }
+            },
+            option,
+        )
+        .await
+        .unwrap()
+        .ok_or_else(magic_link_error)?;
+
+    let claims = Claims::new(
+        find_user._id.to_string(),
+        find_user.username.clone(),
+        find_user.verified_email,
+        find_user.modes.clone(),
+    );
+
+    // Response token.
+    create_login_token(
+        db,
+        &config.private_key,
+        &find_user,
+        claims,
+        LoginMethod::MagicLink,
+        &request_client,
+    )
+    .await
+}
+
+#[doc(hidden)]
+pub fn stage() -> AdHoc {
+    AdHoc::on_ignite("load magic link stage", |rocket| async {
+        rocket.mount("/user", routes![send_magic_link, magic_link_login])
+    })
+}
@@ -0,0 +1,380 @@
+use crate::apis::authentication::data::RequestClient;
+use crate::apis::authentication::util::{create_token, find_login_user, record_login_event};
+use crate::apis::user::lockout::{
+    is_login_locked, record_login_failure, reset_login_failures, send_unlock_code,
+};
+use crate::data::auth_data::{
+    AuthError, Claims, LoginUserData, MfaClaims, MfaLoginData, RecoveryCodes, Token, TotpCodeData,
+    TotpSecret,
+};
+use crate::data::code::Code;
+use crate::data::response::Response;
+use crate::Config;
+use database::{doc, mongodb::bson::oid::ObjectId, Database};
+use rocket::fairing::AdHoc;
+use rocket::form::Form;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::State;
+use std::time::{SystemTime, UNIX_EPOCH};
+use util::bcrypt::{password_hash, verify_password};
+use util::jwt::verify_token;
+use util::totp::{create_totp_secret, create_totp_uri, verify_totp_code, verify_totp_step};
+use util::util::create_random_token;
+
+/// The number of the recovery codes
+const RECOVERY_CODE_COUNT: usize = 10;
+
+type MfaError = (Status, Json<Response<String>>);
+
+fn mfa_code_error() -> MfaError {
+    (
+        Status::Unauthorized,
+        Response::new(Code::MfaCodeError, None),
+    )
+}
+
+/// # Enroll TOTP two-factor authentication
+/// Create a new TOTP secret, two-factor authentication is enabled after the secret is confirmed by `/user/mfa/totp/confirm`.
+/// ## Request
+/// - Path `/user/mfa/totp`
+/// - Method: `POST`
+/// - [X] Authorization
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::AuthError]
+///     - [Code::LoginUserNotFoundError]
+///     - [Code::MfaAlreadyEnabled]
+/// - Content
+///     - [TotpSecret]
+/// ## Curl Example
+/// ```bash
+/// curl -X POST -H "Authorization: Bearer {Token}" http://<host>/user/mfa/totp
+/// ```
+#[post("/mfa/totp")]
+async fn enroll_totp(
+    login_user_data: Result<LoginUserData, AuthError>,
+    db: &State<Database>,
+) -> Result<Json<Response<TotpSecret>>, MfaError> {
+    let find_user = find_login_user(login_user_data, db).await?;
+
+    if find_user.totp_secret.is_some() {
+        return Err((
+            Status::Conflict,
+            Response::new(Code::MfaAlreadyEnabled, None),
+        ));
+    }
+
+    let secret = create_totp_secret();
+
+    db.user
+        .as_ref()
+        .unwrap()
+        .update_one(
+            doc! { "_id": find_user._id },
+            doc! {
+                "$set": {
+                    "totp_pending_secret": &secret
+                }
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    Ok(Response::new(
+        Code::Ok,
+        Some(TotpSecret {
+            uri: create_totp_uri(&secret, &find_user.email, "Lipoic"),
+            secret,
+        }),
+    ))
+}
+
+/// # Confirm TOTP two-factor authentication
+/// Enable two-factor authentication with a code of the secret from `/user/mfa/totp`.
+/// ## Request
+/// - Path `/user/mfa/totp/confirm`
+/// - Method: `POST`
+/// - FromData [TotpCodeData]
+/// - [X] Authorization
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::AuthError]
+///     - [Code::LoginUserNotFoundError]
+///     - [Code::MfaCodeError]
+/// - Content
+///     - [RecoveryCodes] - The recovery codes, only responded once.
+/// ## Curl Example
+/// ```bash
+/// curl -X POST -H "Authorization: Bearer {Token}" -F code=123456 http://<host>/user/mfa/totp/confirm
+/// ```
+#[post("/mfa/totp/confirm", data = "<totp_code_data>")]
+async fn confirm_totp(
+    totp_code_data: Form<TotpCodeData>,
+    login_user_data: Result<LoginUserData, AuthError>,
+    db: &State<Database>,
+) -> Result<Json<Response<RecoveryCodes>>, MfaError> {
+    let find_user = find_login_user(login_user_data, db).await?;
+
+    let secret = match find_user.totp_pending_secret {
+        Some(secret) if verify_totp_code(&secret, &totp_code_data.code) => secret,
+        _ => return Err(mfa_code_error()),
+    };
+
+    let recovery_codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| create_random_token(10))
+        .collect();
+    let recovery_code_hashes: Vec<String> = recovery_codes
+        .iter()
+        .map(|code| password_hash(code).unwrap())
+        .collect();
+
+    db.user
+        .as_ref()
+        .unwrap()
+        .update_one(
+            doc! { "_id": find_user._id },
+            doc! {
+                "$set": {
+                    "totp_secret": secret,
+                    "totp_pending_secret": null,
+                    "recovery_codes": recovery_code_hashes
+                }
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    Ok(Response::new(
+        Code::Ok,
+        Some(RecoveryCodes { recovery_codes }),
+    ))
+}
+
+/// # Disable TOTP two-factor authentication
+/// ## Request
+/// - Path `/user/mfa/totp`
+/// - Method: `DELETE`
+/// - FromData [TotpCodeData]
+/// - [X] Authorization
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::AuthError]
+///     - [Code::LoginUserNotFoundError]
+///     - [Code::MfaCodeError]
+/// ## Curl Example
+/// ```bash
+/// curl -X DELETE -H "Authorization: Bearer {Token}" -F code=123456 http://<host>/user/mfa/totp
+/// ```
+#[delete("/mfa/totp", data = "<totp_code_data>")]
+async fn disable_totp(
+    totp_code_data: Form<TotpCodeData>,
+    login_user_data: Result<LoginUserData, AuthError>,
+    db: &State<Database>,
+) -> Result<Json<Response<String>>, MfaError> {
+    let find_user = find_login_user(login_user_data, db).await?;
+
+    match &find_user.totp_secret {
+        Some(secret) if verify_totp_code(secret, &totp_code_data.code) => {}
+        _ => return Err(mfa_code_error()),
+    }
+
+    db.user
+        .as_ref()
+        .unwrap()
+        .update_one(
+            doc! { "_id": find_user._id },
+            doc! {
+                "$set": {
+                    "totp_secret": null,
+                    "totp_last_step": null,
+                    "recovery_codes": []
+                }
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    // Response Ok.
+    Ok(Response::new(Code::Ok, None))
+}
+
+/// # Two-factor authentication login
+/// Exchange the [MfaToken](crate::data::auth_data::MfaToken) from `/user/login` for the login token.
+/// ## Request
+/// - Path `/user/login/mfa`
+/// - Method: `POST`
+/// - FromData [MfaLoginData]
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::MfaTokenError]
+///     - [Code::MfaCodeError]
+///     - [Code::LoginLocked]
+///     - [Code::AccountPendingDeletion]
+///     - [Code::AccountSuspended]
+/// - Content
+///     - [Token] - A JWT token and a refresh token.
+/// ## Curl Example
+/// ```bash
+/// curl -X POST -F mfa_token={mfa_token} -F code=123456 http://<host>/user/login/mfa
+/// ```
+#[post("/login/mfa", data = "<mfa_login_data>")]
+async fn login_mfa(
+    mfa_login_data: Form<MfaLoginData>,
+    db: &State<Database>,
+    config: &State<Config>,
+    request_client: RequestClient,
+) -> Result<Json<Response<Token>>, MfaError> {
+    let mfa_token_error = || {
+        (
+            Status::Unauthorized,
+            Response::new(Code::MfaTokenError, None),
+        )
+    };
+
+    let mfa_claims = verify_token::<MfaClaims>(
+        mfa_login_data.mfa_token.clone(),
+        config.public_key.as_bytes(),
+    )
+    .map_err(|_| mfa_token_error())?
+    .claims;
+
+    let find_user = db
+        .user
+        .as_ref()
+        .unwrap()
+        .find_one(
+            doc! {
+                "_id": ObjectId::parse_str(&mfa_claims.id).map_err(|_| mfa_token_error())?
+            },
+            None,
+        )
+        .await
+        .unwrap()
+        .ok_or_else(mfa_token_error)?;
+
+    if is_login_locked(db, &find_user.email, &request_client.ip)
+        .await
+        .unwrap()
+    {
+        return Err((
+            Status::TooManyRequests,
+            Response::new(Code::LoginLocked, None),
+        ));
+    }
+
+    if find_user.delete_at.is_some() {
+        return Err((
+            Status::Forbidden,
+            Response::new(Code::AccountPendingDeletion, None),
+        ));
+    }
+    if find_user.suspended_at.is_some() {
+        return Err((
+            Status::Forbidden,
+            Response::new(Code::AccountSuspended, None),
+        ));
+    }
+
+    let secret = find_user.totp_secret.as_ref().ok_or_else(mfa_token_error)?;
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs();
+
+    let update = match verify_totp_step(secret, &mfa_login_data.code, now) {
+        // The code of the accepted step or any step before it can't be used again.
+        Some(step) => Some((
+            doc! {
+                "_id": find_user._id,
+                "totp_last_step": { "$not": { "$gte": step as i64 } }
+            },
+            doc! { "$set": { "totp_last_step": step as i64 } },
+        )),
+        // Try the recovery codes, each recovery code can only be used once.
+        None => find_user
+            .recovery_codes
+            .iter()
+            .find(|hash| verify_password(hash.to_string(), &mfa_login_data.code).unwrap())
+            .map(|recovery_code_hash| {
+                (
+                    doc! {
+                        "_id": find_user._id,
+                        "recovery_codes": recovery_code_hash
+                    },
+                    doc! { "$pull": { "recovery_codes": recovery_code_hash } },
+                )
+            }),
+    };
+
+    // The code is only accepted by the request which consumed it.
+    let accepted = match update {
+        Some((filter, update)) => {
+            db.user
+                .as_ref()
+                .unwrap()
+                .update_one(filter, update, None)
+                .await
+                .unwrap()
+                .modified_count
+                == 1
+        }
+        None => false,
+    };
+
+    record_login_event(
+        db,
+        find_user._id,
+        mfa_claims.method,
+        accepted,
+        &request_client,
+    )
+    .await
+    .unwrap();
+
+    if !accepted {
+        let locked = record_login_failure(db, &find_user.email, &request_client.ip)
+            .await
+            .unwrap();
+        if locked {
+            send_unlock_code(config, &find_user.email);
+        }
+
+        return Err(mfa_code_error());
+    }
+
+    reset_login_failures(db, &find_user.email).await.unwrap();
+
+    let mut claims = Claims::new(
+        find_user._id.to_string(),
+        find_user.username,
+        find_user.verified_email,
+        find_user.modes,
+    );
+    claims.auth_time = mfa_claims.auth_time;
+
+    let token = create_token(db, &config.private_key, claims, None, &request_client)
+        .await
+        .unwrap();
+
+    // Response JWT.
+    Ok(Response::new(Code::Ok, Some(token)))
+}
+
+#[doc(hidden)]
+pub fn stage() -> AdHoc {
+    AdHoc::on_ignite("load mfa stage", |rocket| async {
+        rocket.mount(
+            "/user",
+            routes![enroll_totp, confirm_totp, disable_totp, login_mfa],
+        )
+    })
+}
@@ -1 +1,10 @@
-pub mod api;
\ No newline at end of file
+pub mod api;
+pub mod connect;
+pub mod delete;
+pub mod export;
+pub mod lockout;
+pub mod magic_link;
+pub mod mfa;
+pub mod passkey;
+pub mod security;
+pub mod teacher;
@@ -0,0 +1,635 @@
+use crate::apis::authentication::data::RequestClient;
+use crate::apis::authentication::util::{create_token, find_login_user, record_login_event};
+use crate::data::auth_data::{AuthError, Claims, LoginUserData, MfaClaims, Token};
+use crate::data::code::Code;
+use crate::data::passkey::{
+    PasskeyAuthenticatorSelection, PasskeyClaims, PasskeyCreationOptions,
+    PasskeyCredentialDescriptor, PasskeyCredentialParameter, PasskeyInfo, PasskeyLoginData,
+    PasskeyLoginOptionsData, PasskeyOptions, PasskeyRegisterData, PasskeyRelyingParty,
+    PasskeyRequestOptions, PasskeyUser,
+};
+use crate::data::response::Response;
+use crate::Config;
+use database::model::auth::login_event::LoginMethod;
+use database::model::auth::passkey_challenge::PasskeyChallenge;
+use database::model::auth::user::{Passkey, User};
+use database::mongodb::bson::{self, oid::ObjectId, DateTime};
+use database::{doc, Database};
+use rocket::fairing::AdHoc;
+use rocket::form::Form;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::State;
+use util::jwt::{create_jwt_token, verify_token, TokenPurpose};
+use util::util::create_exp;
+use util::webauthn::{
+    base64_url_decode, base64_url_encode, create_challenge, verify_authentication,
+    verify_registration, RelyingParty, COSE_ALG_EDDSA, COSE_ALG_ES256, COSE_ALG_RS256,
+};
+
+/// The timeout of the passkey ceremonies in seconds
+const PASSKEY_TIMEOUT: usize = 60 * 5;
+
+type PasskeyError = (Status, Json<Response<String>>);
+
+fn passkey_error() -> PasskeyError {
+    (
+        Status::Unauthorized,
+        Response::new(Code::PasskeyError, None),
+    )
+}
+
+fn passkey_state_error() -> PasskeyError {
+    (
+        Status::Unauthorized,
+        Response::new(Code::PasskeyStateError, None),
+    )
+}
+
+fn credential_descriptors(user: &User) -> Vec<PasskeyCredentialDescriptor> {
+    user.passkeys
+        .iter()
+        .map(|passkey| PasskeyCredentialDescriptor {
+            credential_type: "public-key",
+            id: passkey.credential_id.clone(),
+        })
+        .collect()
+}
+
+/// Create the ceremony state token, and store the challenge until it is used
+async fn create_state(
+    db: &Database,
+    config: &Config,
+    challenge: &str,
+    ceremony: &str,
+    id: Option<String>,
+    auth_time: Option<usize>,
+    method: Option<LoginMethod>,
+) -> String {
+    db.passkey_challenge
+        .as_ref()
+        .unwrap()
+        .insert_one(
+            PasskeyChallenge {
+                challenge: challenge.to_string(),
+                exp: DateTime::from_millis(create_exp(PASSKEY_TIMEOUT) as i64 * 1000),
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    create_jwt_token(
+        config.private_key.as_bytes(),
+        PasskeyClaims {
+            exp: create_exp(PASSKEY_TIMEOUT),
+            purpose: TokenPurpose::Passkey,
+            challenge: challenge.to_string(),
+            ceremony: ceremony.to_string(),
+            id,
+            auth_time,
+            method,
+        },
+    )
+    .unwrap()
+}
+
+/// Verify the ceremony state token, and consume its challenge so the state can only be used once
+async fn verify_state(
+    db: &Database,
+    config: &Config,
+    state: &str,
+    ceremony: &str,
+) -> Result<PasskeyClaims, PasskeyError> {
+    let claims = verify_token::<PasskeyClaims>(state.to_string(), config.public_key.as_bytes())
+        .map_err(|_| passkey_state_error())?
+        .claims;
+
+    if claims.ceremony != ceremony {
+        return Err(passkey_state_error());
+    }
+
+    db.passkey_challenge
+        .as_ref()
+        .unwrap()
+        .find_one_and_delete(doc! { "challenge": &claims.challenge }, None)
+        .await
+        .unwrap()
+        .ok_or_else(passkey_state_error)?;
+
+    Ok(claims)
+}
+
+/// # Get passkey registration options
+/// ## Request
+/// - Path `/user/passkey/register/options`
+/// - Method: `POST`
+/// - [X] Authorization
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::AuthError]
+///     - [Code::LoginUserNotFoundError]
+/// - Content
+///     - [PasskeyOptions] of [PasskeyCreationOptions]
+/// ## Curl Example
+/// ```bash
+/// curl -X POST -H "Authorization: Bearer {Token}" http://<host>/user/passkey/register/options
+/// ```
+#[post("/passkey/register/options")]
+async fn passkey_register_options(
+    login_user_data: Result<LoginUserData, AuthError>,
+    db: &State<Database>,
+    config: &State<Config>,
+) -> Result<Json<Response<PasskeyOptions<PasskeyCreationOptions>>>, PasskeyError> {
+    let find_user = find_login_user(login_user_data, db).await?;
+
+    let challenge = create_challenge();
+    let state = create_state(
+        db,
+        config,
+        &challenge,
+        "register",
+        Some(find_user._id.to_string()),
+        None,
+        None,
+    )
+    .await;
+
+    Ok(Response::new(
+        Code::Ok,
+        Some(PasskeyOptions {
+            state,
+            public_key: PasskeyCreationOptions {
+                challenge,
+                rp: PasskeyRelyingParty {
+                    id: config.webauthn_rp_id.clone(),
+                    name: config.webauthn_rp_name.clone(),
+                },
+                user: PasskeyUser {
+                    id: base64_url_encode(&find_user._id.bytes()),
+                    name: find_user.email.clone(),
+                    display_name: find_user.username.clone(),
+                },
+                pub_key_cred_params: [COSE_ALG_ES256, COSE_ALG_EDDSA, COSE_ALG_RS256]
+                    .into_iter()
+                    .map(|alg| PasskeyCredentialParameter {
+                        credential_type: "public-key",
+                        alg,
+                    })
+                    .collect(),
+                timeout: PASSKEY_TIMEOUT * 1000,
+                exclude_credentials: credential_descriptors(&find_user),
+                authenticator_selection: PasskeyAuthenticatorSelection {
+                    resident_key: "preferred",
+                    user_verification: "preferred",
+                },
+                attestation: "none",
+            },
+        }),
+    ))
+}
+
+/// # Register a passkey
+/// ## Request
+/// - Path `/user/passkey/register`
+/// - Method: `POST`
+/// - FromData [PasskeyRegisterData]
+/// - [X] Authorization
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::AuthError]
+///     - [Code::LoginUserNotFoundError]
+///     - [Code::PasskeyStateError]
+///     - [Code::PasskeyError]
+/// - Content
+///     - [PasskeyInfo]
+/// ## Curl Example
+/// ```bash
+/// curl -X POST -H "Authorization: Bearer {Token}" -F state={state} -F client_data_json={client_data_json} -F attestation_object={attestation_object} -F name=Chromebook http://<host>/user/passkey/register
+/// ```
+#[post("/passkey/register", data = "<passkey_register_data>")]
+async fn passkey_register(
+    passkey_register_data: Form<PasskeyRegisterData>,
+    login_user_data: Result<LoginUserData, AuthError>,
+    db: &State<Database>,
+    config: &State<Config>,
+) -> Result<Json<Response<PasskeyInfo>>, PasskeyError> {
+    let find_user = find_login_user(login_user_data, db).await?;
+    let state = verify_state(db, config, &passkey_register_data.state, "register").await?;
+
+    if state.id != Some(find_user._id.to_string()) {
+        return Err(passkey_state_error());
+    }
+
+    let credential = verify_registration(
+        &RelyingParty {
+            id: &config.webauthn_rp_id,
+            origins: &config.allowed_origins,
+        },
+        &state.challenge,
+        &base64_url_decode(&passkey_register_data.client_data_json).map_err(|_| passkey_error())?,
+        &base64_url_decode(&passkey_register_data.attestation_object)
+            .map_err(|_| passkey_error())?,
+        false,
+    )
+    .map_err(|_| passkey_error())?;
+
+    let credential_id = base64_url_encode(&credential.credential_id);
+
+    // The passkey can only be registered once.
+    if db
+        .user
+        .as_ref()
+        .unwrap()
+        .find_one(doc! { "passkeys.credential_id": &credential_id }, None)
+        .await
+        .unwrap()
+        .is_some()
+    {
+        return Err((Status::Conflict, Response::new(Code::PasskeyError, None)));
+    }
+
+    let passkey = Passkey {
+        credential_id,
+        public_key: base64_url_encode(&credential.public_key),
+        sign_count: credential.sign_count as i64,
+        name: passkey_register_data
+            .name
+            .clone()
+            .unwrap_or_else(|| "Passkey".to_string()),
+        created_at: DateTime::now(),
+    };
+
+    db.user
+        .as_ref()
+        .unwrap()
+        .update_one(
+            doc! { "_id": find_user._id },
+            doc! {
+                "$push": {
+                    "passkeys": bson::to_bson(&passkey).unwrap()
+                }
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    Ok(Response::new(
+        Code::Ok,
+        Some(PasskeyInfo {
+            credential_id: passkey.credential_id,
+            name: passkey.name,
+            created_at: passkey.created_at.timestamp_millis(),
+        }),
+    ))
+}
+
+/// # Get passkey login options
+/// Login with a passkey without password, or use a passkey as the second factor
+/// with the [MfaToken](crate::data::auth_data::MfaToken) from `/user/login`.
+/// ## Request
+/// - Path `/user/passkey/login/options`
+/// - Method: `POST`
+/// - FromData [PasskeyLoginOptionsData]
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::MfaTokenError]
+/// - Content
+///     - [PasskeyOptions] of [PasskeyRequestOptions]
+/// ## Curl Example
+/// ```bash
+/// curl -X POST http://<host>/user/passkey/login/options
+/// ```
+#[post("/passkey/login/options", data = "<passkey_login_options_data>")]
+async fn passkey_login_options(
+    passkey_login_options_data: Form<PasskeyLoginOptionsData>,
+    db: &State<Database>,
+    config: &State<Config>,
+) -> Result<Json<Response<PasskeyOptions<PasskeyRequestOptions>>>, PasskeyError> {
+    let challenge = create_challenge();
+
+    let (state, allow_credentials, user_verification) = if let Some(mfa_token) =
+        &passkey_login_options_data.mfa_token
+    {
+        let mfa_token_error = || {
+            (
+                Status::Unauthorized,
+                Response::new(Code::MfaTokenError, None),
+            )
+        };
+
+        let mfa_claims = verify_token::<MfaClaims>(mfa_token.clone(), config.public_key.as_bytes())
+            .map_err(|_| mfa_token_error())?
+            .claims;
+
+        let find_user = db
+            .user
+            .as_ref()
+            .unwrap()
+            .find_one(
+                doc! {
+                    "_id": ObjectId::parse_str(&mfa_claims.id).map_err(|_| mfa_token_error())?
+                },
+                None,
+            )
+            .await
+            .unwrap()
+            .ok_or_else(mfa_token_error)?;
+
+        (
+            create_state(
+                db,
+                config,
+                &challenge,
+                "login",
+                Some(mfa_claims.id),
+                Some(mfa_claims.auth_time),
+                Some(mfa_claims.method),
+            )
+            .await,
+            credential_descriptors(&find_user),
+            "preferred",
+        )
+    } else {
+        // The passkey is the only factor, the user must be verified.
+        (
+            create_state(db, config, &challenge, "login", None, None, None).await,
+            vec![],
+            "required",
+        )
+    };
+
+    Ok(Response::new(
+        Code::Ok,
+        Some(PasskeyOptions {
+            state,
+            public_key: PasskeyRequestOptions {
+                challenge,
+                rp_id: config.webauthn_rp_id.clone(),
+                timeout: PASSKEY_TIMEOUT * 1000,
+                allow_credentials,
+                user_verification,
+            },
+        }),
+    ))
+}
+
+/// # Passkey login
+/// ## Request
+/// - Path `/user/passkey/login`
+/// - Method: `POST`
+/// - FromData [PasskeyLoginData]
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::PasskeyStateError]
+///     - [Code::PasskeyError]
+///     - [Code::AccountPendingDeletion]
+///     - [Code::AccountSuspended]
+/// - Content
+///     - [Token] - A JWT token and a refresh token.
+/// ## Curl Example
+/// ```bash
+/// curl -X POST -F state={state} -F credential_id={credential_id} -F client_data_json={client_data_json} -F authenticator_data={authenticator_data} -F signature={signature} http://<host>/user/passkey/login
+/// ```
+#[post("/passkey/login", data = "<passkey_login_data>")]
+async fn passkey_login(
+    passkey_login_data: Form<PasskeyLoginData>,
+    db: &State<Database>,
+    config: &State<Config>,
+    request_client: RequestClient,
+) -> Result<Json<Response<Token>>, PasskeyError> {
+    let state = verify_state(db, config, &passkey_login_data.state, "login").await?;
+
+    let find_user = db
+        .user
+        .as_ref()
+        .unwrap()
+        .find_one(
+            doc! { "passkeys.credential_id": &passkey_login_data.credential_id },
+            None,
+        )
+        .await
+        .unwrap()
+        .ok_or_else(passkey_error)?;
+
+    if find_user.delete_at.is_some() {
+        return Err((
+            Status::Forbidden,
+            Response::new(Code::AccountPendingDeletion, None),
+        ));
+    }
+    if find_user.suspended_at.is_some() {
+        return Err((
+            Status::Forbidden,
+            Response::new(Code::AccountSuspended, None),
+        ));
+    }
+
+    // The passkey of the second factor must belong to the user of the first factor.
+    if state.id.is_some() && state.id != Some(find_user._id.to_string()) {
+        return Err(passkey_error());
+    }
+
+    let passkey = find_user
+        .passkeys
+        .iter()
+        .find(|passkey| passkey.credential_id == passkey_login_data.credential_id)
+        .ok_or_else(passkey_error)?;
+
+    let sign_count = verify_authentication(
+        &RelyingParty {
+            id: &config.webauthn_rp_id,
+            origins: &config.allowed_origins,
+        },
+        &state.challenge,
+        &base64_url_decode(&passkey.public_key).map_err(|_| passkey_error())?,
+        passkey.sign_count as u32,
+        &base64_url_decode(&passkey_login_data.client_data_json).map_err(|_| passkey_error())?,
+        &base64_url_decode(&passkey_login_data.authenticator_data).map_err(|_| passkey_error())?,
+        &base64_url_decode(&passkey_login_data.signature).map_err(|_| passkey_error())?,
+        state.id.is_none(),
+    );
+
+    // The sign count is only updated from the count that was verified,
+    // so the concurrent logins with the same assertion can't all succeed.
+    let success = match sign_count {
+        Ok(sign_count) => {
+            db.user
+                .as_ref()
+                .unwrap()
+                .update_one(
+                    doc! {
+                        "_id": find_user._id,
+                        "passkeys": {
+                            "$elemMatch": {
+                                "credential_id": &passkey.credential_id,
+                                "sign_count": passkey.sign_count
+                            }
+                        }
+                    },
+                    doc! {
+                        "$set": {
+                            "passkeys.$.sign_count": sign_count as i64
+                        }
+                    },
+                    None,
+                )
+                .await
+                .unwrap()
+                .matched_count
+                == 1
+        }
+        Err(_) => false,
+    };
+
+    // The login of the two-factor authentication is recorded as its first factor.
+    record_login_event(
+        db,
+        find_user._id,
+        state.method.clone().unwrap_or(LoginMethod::Passkey),
+        success,
+        &request_client,
+    )
+    .await
+    .unwrap();
+
+    if !success {
+        return Err(passkey_error());
+    }
+
+    let mut claims = Claims::new(
+        find_user._id.to_string(),
+        find_user.username,
+        find_user.verified_email,
+        find_user.modes,
+    );
+    if let Some(auth_time) = state.auth_time {
+        claims.auth_time = auth_time;
+    }
+
+    let token = create_token(db, &config.private_key, claims, None, &request_client)
+        .await
+        .unwrap();
+
+    // Response JWT.
+    Ok(Response::new(Code::Ok, Some(token)))
+}
+
+/// # Get the passkeys of the login user
+/// ## Request
+/// - Path `/user/passkeys`
+/// - Method: `GET`
+/// - [X] Authorization
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::AuthError]
+///     - [Code::LoginUserNotFoundError]
+/// - Content
+///     - [Vec]<[PasskeyInfo]>
+/// ## Curl Example
+/// ```bash
+/// curl -X GET -H "Authorization: Bearer {Token}" http://<host>/user/passkeys
+/// ```
+#[get("/passkeys")]
+async fn get_passkeys(
+    login_user_data: Result<LoginUserData, AuthError>,
+    db: &State<Database>,
+) -> Result<Json<Response<Vec<PasskeyInfo>>>, PasskeyError> {
+    let find_user = find_login_user(login_user_data, db).await?;
+
+    Ok(Response::new(
+        Code::Ok,
+        Some(
+            find_user
+                .passkeys
+                .into_iter()
+                .map(|passkey| PasskeyInfo {
+                    credential_id: passkey.credential_id,
+                    name: passkey.name,
+                    created_at: passkey.created_at.timestamp_millis(),
+                })
+                .collect(),
+        ),
+    ))
+}
+
+/// # Delete a passkey
+/// ## Request
+/// - Path `/user/passkeys/<credential_id>`
+/// - Method: `DELETE`
+/// - [X] Authorization
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::AuthError]
+///     - [Code::LoginUserNotFoundError]
+///     - [Code::PasskeyNotFound]
+///     - [Code::LastLoginMethod]
+/// ## Curl Example
+/// ```bash
+/// curl -X DELETE -H "Authorization: Bearer {Token}" http://<host>/user/passkeys/{credential_id}
+/// ```
+#[delete("/passkeys/<credential_id>")]
+async fn delete_passkey(
+    credential_id: String,
+    login_user_data: Result<LoginUserData, AuthError>,
+    db: &State<Database>,
+) -> Result<Json<Response<String>>, PasskeyError> {
+    let find_user = find_login_user(login_user_data, db).await?;
+
+    if !find_user
+        .passkeys
+        .iter()
+        .any(|passkey| passkey.credential_id == credential_id)
+    {
+        return Err((Status::NotFound, Response::new(Code::PasskeyNotFound, None)));
+    }
+
+    if find_user.passkeys.len() == 1
+        && find_user.password_hash.is_none()
+        && find_user.connects.is_empty()
+    {
+        return Err((Status::Conflict, Response::new(Code::LastLoginMethod, None)));
+    }
+
+    db.user
+        .as_ref()
+        .unwrap()
+        .update_one(
+            doc! { "_id": find_user._id },
+            doc! {
+                "$pull": {
+                    "passkeys": { "credential_id": &credential_id }
+                }
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    // Response Ok.
+    Ok(Response::new(Code::Ok, None))
+}
+
+#[doc(hidden)]
+pub fn stage() -> AdHoc {
+    AdHoc::on_ignite("load passkey stage", |rocket| async {
+        rocket.mount(
+            "/user",
+            routes![
+                passkey_register_options,
+                passkey_register,
+                passkey_login_options,
+                passkey_login,
+                get_passkeys,
+                delete_passkey
+            ],
+        )
+    })
+}
@@ -0,0 +1,177 @@
+use crate::apis::authentication::util::{find_login_user, revoke_session_by_id};
+use crate::data::auth_data::{AuthError, LoginUserData};
+use crate::data::code::Code;
+use crate::data::response::Response;
+use crate::data::user::{LoginEventInfo, SessionInfo};
+use database::mongodb::bson::{oid::ObjectId, DateTime};
+use database::mongodb::options::FindOptions;
+use database::{doc, Database};
+use rocket::fairing::AdHoc;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::State;
+
+/// The default number of the login events
+const DEFAULT_LOGIN_EVENT_LIMIT: i64 = 50;
+/// The maximum number of the login events
+const MAX_LOGIN_EVENT_LIMIT: i64 = 100;
+
+type SecurityError = (Status, Json<Response<String>>);
+
+/// # Get the login history of the login user
+/// The login events are kept for 90 days, the latest event is the first.
+/// ## Request
+/// - Path `/user/security/logins`
+/// - Method: `GET`
+/// - Parameters
+///     - `limit` - Optional, the number of the events, 50 by default and 100 at most
+/// - [X] Authorization
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::AuthError]
+///     - [Code::LoginUserNotFoundError]
+/// - Content
+///     - [Vec]<[LoginEventInfo]>
+/// ## Curl Example
+/// ```bash
+/// curl -X GET -H "Authorization: Bearer {Token}" http://<host>/user/security/logins?limit=20
+/// ```
+#[get("/security/logins?<limit>")]
+async fn get_login_events(
+    limit: Option<i64>,
+    login_user_data: Result<LoginUserData, AuthError>,
+    db: &State<Database>,
+) -> Result<Json<Response<Vec<LoginEventInfo>>>, SecurityError> {
+    let find_user = find_login_user(login_user_data, db).await?;
+
+    let limit = limit
+        .unwrap_or(DEFAULT_LOGIN_EVENT_LIMIT)
+        .clamp(1, MAX_LOGIN_EVENT_LIMIT);
+    let option = FindOptions::builder()
+        .sort(doc! { "time": -1 })
+        .limit(limit)
+        .build();
+
+    let mut cursor = db
+        .login_events
+        .as_ref()
+        .unwrap()
+        .find(doc! { "user_id": find_user._id }, option)
+        .await
+        .unwrap();
+
+    let mut login_events = vec![];
+    while cursor.advance().await.unwrap() {
+        login_events.push(LoginEventInfo::from(cursor.deserialize_current().unwrap()));
+    }
+
+    Ok(Response::new(Code::Ok, Some(login_events)))
+}
+
+/// # Get the sessions of the login user
+/// ## Request
+/// - Path `/user/sessions`
+/// - Method: `GET`
+/// - [X] Authorization
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::AuthError]
+///     - [Code::LoginUserNotFoundError]
+/// - Content
+///     - [Vec]<[SessionInfo]>
+/// ## Curl Example
+/// ```bash
+/// curl -X GET -H "Authorization: Bearer {Token}" http://<host>/user/sessions
+/// ```
+#[get("/sessions")]
+async fn get_sessions(
+    login_user_data: Result<LoginUserData, AuthError>,
+    db: &State<Database>,
+) -> Result<Json<Response<Vec<SessionInfo>>>, SecurityError> {
+    let sid = login_user_data
+        .as_ref()
+        .map(|data| data.sid.clone())
+        .unwrap_or_default();
+    let find_user = find_login_user(login_user_data, db).await?;
+
+    let option = FindOptions::builder()
+        .sort(doc! { "last_seen": -1 })
+        .build();
+
+    let mut cursor = db
+        .session
+        .as_ref()
+        .unwrap()
+        .find(
+            doc! {
+                "user_id": find_user._id,
+                // The expired document may not be removed yet.
+                "exp": { "$gt": DateTime::now() }
+            },
+            option,
+        )
+        .await
+        .unwrap();
+
+    let mut sessions = vec![];
+    while cursor.advance().await.unwrap() {
+        sessions.push(SessionInfo::new(
+            cursor.deserialize_current().unwrap(),
+            &sid,
+        ));
+    }
+
+    Ok(Response::new(Code::Ok, Some(sessions)))
+}
+
+/// # Revoke a session of the login user
+/// Logout the session, e.g. on a lost device.
+/// ## Request
+/// - Path `/user/sessions/<id>`
+///     - `id` - The id of the session, see `/user/sessions`
+/// - Method: `DELETE`
+/// - [X] Authorization
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::AuthError]
+///     - [Code::LoginUserNotFoundError]
+///     - [Code::SessionNotFound]
+/// ## Curl Example
+/// ```bash
+/// curl -X DELETE -H "Authorization: Bearer {Token}" http://<host>/user/sessions/{id}
+/// ```
+#[delete("/sessions/<id>")]
+async fn revoke_session(
+    id: &str,
+    login_user_data: Result<LoginUserData, AuthError>,
+    db: &State<Database>,
+) -> Result<Json<Response<String>>, SecurityError> {
+    let find_user = find_login_user(login_user_data, db).await?;
+
+    let session_not_found = || (Status::NotFound, Response::new(Code::SessionNotFound, None));
+
+    let family = ObjectId::parse_str(id).map_err(|_| session_not_found())?;
+
+    if !revoke_session_by_id(db, find_user._id, family)
+        .await
+        .unwrap()
+    {
+        return Err(session_not_found());
+    }
+
+    // Response Ok.
+    Ok(Response::new(Code::Ok, None))
+}
+
+#[doc(hidden)]
+pub fn stage() -> AdHoc {
+    AdHoc::on_ignite("load security stage", |rocket| async {
+        rocket.mount(
+            "/user",
+            routes![get_login_events, get_sessions, revoke_session],
+        )
+    })
+}
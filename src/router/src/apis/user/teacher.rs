@@ -0,0 +1,326 @@
+use crate::apis::authentication::util::find_login_user;
+use crate::data::auth_data::{
+    AuthError, LoginUserData, StaffEmailConfirmData, TeacherVerificationData, MAX_DOCUMENT_SIZE,
+    STAFF_EMAIL_EXP,
+};
+use crate::data::code::Code;
+use crate::data::response::Response;
+use crate::data::user::TeacherVerificationInfo;
+use crate::Config;
+use database::model::auth::user::Role;
+use database::model::teacher_verification::{
+    TeacherVerification, VerificationDocument, VerificationStatus,
+};
+use database::mongodb::bson::spec::BinarySubtype;
+use database::mongodb::bson::{oid::ObjectId, Binary, DateTime};
+use database::mongodb::options::FindOneOptions;
+use database::{doc, is_duplicate_key_error, Database};
+use rocket::fairing::AdHoc;
+use rocket::form::Form;
+use rocket::fs::TempFile;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::tokio::fs;
+use rocket::State;
+use util::email::{send_staff_email_confirm_email, StaffEmailClaims};
+use util::jwt::{create_jwt_token, verify_token, TokenPurpose};
+use util::role::roles_include;
+use util::util::{create_exp, create_random_token};
+
+type TeacherError = (Status, Json<Response<String>>);
+
+/// # Request the teacher verification
+/// The teacher role and mode are granted after the request is approved by the admins.
+/// A staff email or a document is required, a confirmation link is sent to the staff email.
+/// ## Request
+/// - Path `/user/teacher-verification`
+/// - Method: `POST`
+/// - FromData [TeacherVerificationData] (multipart form)
+/// - [X] Authorization
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::AuthError]
+///     - [Code::LoginUserNotFoundError]
+///     - [Code::AlreadyTeacher]
+///     - [Code::TeacherVerificationPending]
+///     - [Code::TeacherVerificationInvalid]
+///     - [Code::DocumentTooLarge]
+///     - [Code::DocumentUploadError]
+/// - Content
+///     - [TeacherVerificationInfo]
+/// ## Curl Example
+/// ```bash
+/// curl -X POST -H "Authorization: Bearer {Token}" -F school='Lipoic High School' -F staff_email=teacher@school.edu.tw -F document=@certificate.pdf http://<host>/user/teacher-verification
+/// ```
+#[post("/teacher-verification", data = "<teacher_verification_data>")]
+async fn request_teacher_verification(
+    mut teacher_verification_data: Form<TeacherVerificationData<'_>>,
+    login_user_data: Result<LoginUserData, AuthError>,
+    db: &State<Database>,
+    config: &State<Config>,
+) -> Result<Json<Response<TeacherVerificationInfo>>, TeacherError> {
+    let find_user = find_login_user(login_user_data, db).await?;
+
+    if roles_include(&find_user.roles, Role::Teacher) {
+        return Err((Status::Conflict, Response::new(Code::AlreadyTeacher, None)));
+    }
+
+    let school = teacher_verification_data.school.trim().to_string();
+    let staff_email = teacher_verification_data
+        .staff_email
+        .as_ref()
+        .map(|email| email.trim().to_string())
+        .filter(|email| !email.is_empty());
+
+    if school.is_empty() || (staff_email.is_none() && teacher_verification_data.document.is_none())
+    {
+        return Err((
+            Status::BadRequest,
+            Response::new(Code::TeacherVerificationInvalid, None),
+        ));
+    }
+
+    let teacher_verification = db.teacher_verification.as_ref().unwrap();
+
+    if teacher_verification
+        .find_one(
+            doc! {
+                "user_id": find_user._id,
+                "status": "Pending"
+            },
+            None,
+        )
+        .await
+        .unwrap()
+        .is_some()
+    {
+        return Err((
+            Status::Conflict,
+            Response::new(Code::TeacherVerificationPending, None),
+        ));
+    }
+
+    let document = match teacher_verification_data.document.as_mut() {
+        Some(file) => Some(read_document(file).await?),
+        None => None,
+    };
+
+    let verification = TeacherVerification {
+        _id: ObjectId::new(),
+        user_id: find_user._id,
+        school,
+        staff_email: staff_email.clone(),
+        staff_email_verified: false,
+        document,
+        note: teacher_verification_data.note.clone(),
+        status: VerificationStatus::Pending,
+        created_at: DateTime::now(),
+        reviewer_id: None,
+        review_note: None,
+        reviewed_at: None,
+    };
+
+    // The unique index of the pending requests rejects the concurrent requests
+    match teacher_verification.insert_one(&verification, None).await {
+        Err(err) if is_duplicate_key_error(&err) => {
+            return Err((
+                Status::Conflict,
+                Response::new(Code::TeacherVerificationPending, None),
+            ));
+        }
+        result => result.unwrap(),
+    };
+
+    if let Some(staff_email) = staff_email {
+        let code = create_jwt_token(
+            config.private_key.as_bytes(),
+            StaffEmailClaims {
+                exp: create_exp(STAFF_EMAIL_EXP),
+                purpose: TokenPurpose::StaffEmail,
+                verification_id: verification._id.to_hex(),
+                staff_email: staff_email.clone(),
+            },
+        )
+        .unwrap();
+
+        send_staff_email_confirm_email(
+            &config.google_account_email,
+            &config.google_account_password,
+            &config.staff_email_confirm_url,
+            code,
+            &staff_email,
+        );
+    }
+
+    Ok(Response::new(
+        Code::Ok,
+        Some(TeacherVerificationInfo::from(verification)),
+    ))
+}
+
+/// Read the uploaded document, it's stored in the request document so the size is limited by [MAX_DOCUMENT_SIZE]
+async fn read_document(file: &mut TempFile<'_>) -> Result<VerificationDocument, TeacherError> {
+    if file.len() > MAX_DOCUMENT_SIZE {
+        return Err((
+            Status::PayloadTooLarge,
+            Response::new(Code::DocumentTooLarge, None),
+        ));
+    }
+
+    let name = file.name().unwrap_or("document").to_string();
+    let content_type = file
+        .content_type()
+        .map(|content_type| content_type.to_string())
+        .unwrap_or_else(|| "application/octet-stream".to_string());
+
+    let path = std::env::temp_dir().join(create_random_token(32));
+    let bytes = match file.persist_to(&path).await {
+        Ok(_) => fs::read(&path).await,
+        Err(err) => Err(err),
+    };
+    fs::remove_file(&path).await.ok();
+
+    let bytes = bytes.map_err(|err| {
+        warn!(
+            "Failed to read the teacher verification document: {:?}",
+            err
+        );
+        (
+            Status::InternalServerError,
+            Response::new(Code::DocumentUploadError, None),
+        )
+    })?;
+
+    Ok(VerificationDocument {
+        name,
+        content_type,
+        data: Binary {
+            subtype: BinarySubtype::Generic,
+            bytes,
+        },
+    })
+}
+
+/// # Get the teacher verification of the login user
+/// The latest request is responded.
+/// ## Request
+/// - Path `/user/teacher-verification`
+/// - Method: `GET`
+/// - [X] Authorization
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::AuthError]
+///     - [Code::LoginUserNotFoundError]
+///     - [Code::TeacherVerificationNotFound]
+/// - Content
+///     - [TeacherVerificationInfo]
+/// ## Curl Example
+/// ```bash
+/// curl -X GET -H "Authorization: Bearer {Token}" http://<host>/user/teacher-verification
+/// ```
+#[get("/teacher-verification")]
+async fn get_teacher_verification(
+    login_user_data: Result<LoginUserData, AuthError>,
+    db: &State<Database>,
+) -> Result<Json<Response<TeacherVerificationInfo>>, TeacherError> {
+    let find_user = find_login_user(login_user_data, db).await?;
+
+    let option = FindOneOptions::builder()
+        .sort(doc! { "created_at": -1 })
+        .build();
+
+    let verification = db
+        .teacher_verification
+        .as_ref()
+        .unwrap()
+        .find_one(doc! { "user_id": find_user._id }, option)
+        .await
+        .unwrap()
+        .ok_or((
+            Status::NotFound,
+            Response::new(Code::TeacherVerificationNotFound, None),
+        ))?;
+
+    Ok(Response::new(
+        Code::Ok,
+        Some(TeacherVerificationInfo::from(verification)),
+    ))
+}
+
+/// # Confirm the staff email of the teacher verification
+/// The code is from the email sent to the staff email, the request must be pending.
+/// ## Request
+/// - Path `/user/teacher-verification/staff-email/confirm`
+/// - Method: `POST`
+/// - FromData [StaffEmailConfirmData]
+/// ## Response
+/// - Code
+///     - [Code::Ok]
+///     - [Code::StaffEmailConfirmError]
+/// ## Curl Example
+/// ```bash
+/// curl -X POST -F code={code} http://<host>/user/teacher-verification/staff-email/confirm
+/// ```
+#[post(
+    "/teacher-verification/staff-email/confirm",
+    data = "<staff_email_confirm_data>"
+)]
+async fn confirm_staff_email(
+    staff_email_confirm_data: Form<StaffEmailConfirmData>,
+    db: &State<Database>,
+    config: &State<Config>,
+) -> Result<Json<Response<String>>, TeacherError> {
+    let staff_email_error = || {
+        (
+            Status::Unauthorized,
+            Response::new(Code::StaffEmailConfirmError, None),
+        )
+    };
+
+    let claims = verify_token::<StaffEmailClaims>(
+        staff_email_confirm_data.code.clone(),
+        config.public_key.as_bytes(),
+    )
+    .map_err(|_| staff_email_error())?
+    .claims;
+
+    let result = db
+        .teacher_verification
+        .as_ref()
+        .unwrap()
+        .update_one(
+            doc! {
+                "_id": ObjectId::parse_str(&claims.verification_id)
+                    .map_err(|_| staff_email_error())?,
+                "staff_email": &claims.staff_email,
+                "status": "Pending"
+            },
+            doc! { "$set": { "staff_email_verified": true } },
+            None,
+        )
+        .await
+        .unwrap();
+
+    if result.matched_count == 0 {
+        return Err(staff_email_error());
+    }
+
+    // Response Ok.
+    Ok(Response::new(Code::Ok, None))
+}
+
+#[doc(hidden)]
+pub fn stage() -> AdHoc {
+    AdHoc::on_ignite("load teacher stage", |rocket| async {
+        rocket.mount(
+            "/user",
+            routes![
+                request_teacher_verification,
+                get_teacher_verification,
+                confirm_staff_email
+            ],
+        )
+    })
+}
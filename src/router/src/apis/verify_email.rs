@@ -1,45 +1,69 @@
 use crate::data::code::Code;
-use crate::data::response::Response;
 use crate::Config;
 use database::{doc, Database};
 use rocket::fairing::AdHoc;
-use rocket::response::status::Unauthorized;
 use rocket::response::Redirect;
-use rocket::serde::json::Json;
 use rocket::State;
 use util::email::VerifyEmailClaims;
 use util::jwt::verify_token;
 
+fn verify_email_failed(config: &Config) -> Redirect {
+    Redirect::to(format!(
+        "{}?error={}",
+        config.verify_email_failure_url,
+        Code::VerifyEmailError.code
+    ))
+}
+
+/// # Verify email
+/// The code can only be used once, it is invalid after the email is verified.
+/// ## Request
+/// - Path `/verify-email`
+/// - Method `GET`
+/// - Parameters
+///     - `code` - The code from the verification email
+/// ## Response
+/// - Redirect to `verify_email_success_url` of the config if the email is verified,
+///   otherwise redirect to `verify_email_failure_url` with `error=6` ([Code::VerifyEmailError]).
+/// ## Curl Example
+/// ```bash
+/// curl -X GET http://<host>/verify-email?code={code}
+/// ```
 #[get("/verify-email?<code>")]
-async fn verify_email(
-    code: String,
-    config: &State<Config>,
-    db: &State<Database>,
-) -> Result<Redirect, Unauthorized<Json<Response<String>>>> {
-    if let Ok(verify_user_data) =
-        verify_token::<VerifyEmailClaims>(code, config.public_key.as_bytes())
-    {
-        db.user
-            .as_ref()
-            .unwrap()
-            .find_one_and_update(
-                doc! { "email": &verify_user_data.claims.email },
-                doc! {
-                    "$set": {
-                        "verified_email": true
-                    }
+async fn verify_email(code: String, config: &State<Config>, db: &State<Database>) -> Redirect {
+    let verify_user_data =
+        match verify_token::<VerifyEmailClaims>(code, config.public_key.as_bytes()) {
+            Ok(verify_user_data) => verify_user_data,
+            Err(_) => return verify_email_failed(config),
+        };
+
+    let result = db
+        .user
+        .as_ref()
+        .unwrap()
+        .update_one(
+            doc! {
+                "email": &verify_user_data.claims.email,
+                "verified_email": false
+            },
+            doc! {
+                "$set": {
+                    "verified_email": true
                 },
-                None,
-            )
-            .await
-            .unwrap();
+                "$unset": {
+                    "verify_email_sent_at": ""
+                }
+            },
+            None,
+        )
+        .await
+        .unwrap();
 
-        Ok(Redirect::to("/"))
+    if result.modified_count == 0 {
+        // The email is already verified, or the user is not found.
+        verify_email_failed(config)
     } else {
-        Err(Unauthorized(Some(Response::new(
-            Code::VerifyEmailError,
-            None,
-        ))))
+        Redirect::to(config.verify_email_success_url.clone())
     }
 }
 
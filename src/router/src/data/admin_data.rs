@@ -0,0 +1,103 @@
+use crate::data::user::ConnectInfo;
+use database::model::audit_log::{AuditAction, AuditLog};
+use database::model::auth::user::{Role, User, UserMode};
+use rocket::serde::json::Json;
+use rocket::serde::Serialize;
+
+#[derive(FromForm)]
+pub struct SetRolesData {
+    pub(crate) roles: Json<Vec<Role>>,
+}
+
+#[derive(FromForm)]
+pub struct SetModesData {
+    pub(crate) modes: Json<Vec<UserMode>>,
+}
+
+#[derive(FromForm)]
+pub struct SuspendUserData {
+    /// The reason shown to the user
+    pub(crate) reason: Option<String>,
+}
+
+#[derive(FromForm)]
+pub struct ReviewTeacherData {
+    pub(crate) approved: bool,
+    /// The note to the user, e.g. the reason of the rejection
+    pub(crate) note: Option<String>,
+}
+
+/// The user info for the admins, the secrets (e.g. the password hash) are not included
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct AdminUserInfo {
+    pub(crate) id: String,
+    pub(crate) username: String,
+    pub(crate) email: String,
+    pub(crate) verified_email: bool,
+    pub(crate) modes: Vec<UserMode>,
+    pub(crate) roles: Vec<Role>,
+    pub(crate) connects: Vec<ConnectInfo>,
+    pub(crate) has_password: bool,
+    pub(crate) mfa_enabled: bool,
+    pub(crate) passkey_count: usize,
+    /// The deletion time (as UTC timestamp in milliseconds)
+    pub(crate) delete_at: Option<i64>,
+    /// The suspended time (as UTC timestamp in milliseconds)
+    pub(crate) suspended_at: Option<i64>,
+    pub(crate) suspend_reason: Option<String>,
+}
+
+impl From<User> for AdminUserInfo {
+    fn from(user: User) -> Self {
+        AdminUserInfo {
+            id: user._id.to_hex(),
+            username: user.username,
+            email: user.email,
+            verified_email: user.verified_email,
+            modes: user.modes,
+            roles: user.roles,
+            connects: user.connects.into_iter().map(ConnectInfo::from).collect(),
+            has_password: user.password_hash.is_some(),
+            mfa_enabled: user.totp_secret.is_some(),
+            passkey_count: user.passkeys.len(),
+            delete_at: user.delete_at.map(|delete_at| delete_at.timestamp_millis()),
+            suspended_at: user
+                .suspended_at
+                .map(|suspended_at| suspended_at.timestamp_millis()),
+            suspend_reason: user.suspend_reason,
+        }
+    }
+}
+
+/// A page of the users
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct UserList {
+    /// The number of all the matched users
+    pub(crate) total: u64,
+    pub(crate) users: Vec<AdminUserInfo>,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct AuditLogInfo {
+    pub(crate) id: String,
+    pub(crate) admin_id: String,
+    pub(crate) user_id: String,
+    pub(crate) action: AuditAction,
+    /// The action time (as UTC timestamp in milliseconds)
+    pub(crate) time: i64,
+}
+
+impl From<AuditLog> for AuditLogInfo {
+    fn from(audit_log: AuditLog) -> Self {
+        AuditLogInfo {
+            id: audit_log._id.to_hex(),
+            admin_id: audit_log.admin_id.to_hex(),
+            user_id: audit_log.user_id.to_hex(),
+            action: audit_log.action,
+            time: audit_log.time.timestamp_millis(),
+        }
+    }
+}
@@ -1,14 +1,49 @@
 use crate::data::code::Code;
 use crate::data::response::Response;
 use crate::Config;
-use database::model::auth::user::UserMode;
+use database::model::auth::login_event::LoginMethod;
+use database::model::auth::user::{ConnectAccount, ConnectType, UserMode};
+use database::mongodb::bson::oid::ObjectId;
+use database::{doc, Database};
+use rocket::fs::TempFile;
 use rocket::http::Status;
 use rocket::request::{FromRequest, Outcome};
 use rocket::response::status::Unauthorized;
 use rocket::serde::json::Json;
 use rocket::serde::{Deserialize, Serialize};
 use rocket::{Request, State};
-use util::jwt::verify_token;
+use util::jwt::{verify_token, TokenPurpose};
+use util::purpose_claims;
+use util::util::{create_exp, create_random_token};
+
+/// The access token expiration time (15 minutes)
+pub const ACCESS_TOKEN_EXP: usize = 60 * 15;
+/// The refresh token expiration time (30 days)
+pub const REFRESH_TOKEN_EXP: usize = 60 * 60 * 24 * 30;
+/// The OAuth state expiration time (10 minutes)
+pub const OAUTH_STATE_EXP: usize = 60 * 10;
+/// The link token expiration time (10 minutes)
+pub const LINK_TOKEN_EXP: usize = 60 * 10;
+/// The MFA token expiration time (5 minutes)
+pub const MFA_TOKEN_EXP: usize = 60 * 5;
+/// The verify email code expiration time (1 day)
+pub const VERIFY_EMAIL_EXP: usize = 60 * 60 * 24;
+/// The minimum interval between two verification emails (1 minute)
+pub const VERIFY_EMAIL_RESEND_INTERVAL: i64 = 60;
+/// The reset password code expiration time (15 minutes)
+pub const RESET_PASSWORD_EXP: usize = 60 * 15;
+/// The change email code expiration time (30 minutes)
+pub const CHANGE_EMAIL_EXP: usize = 60 * 30;
+/// The connect confirmation code expiration time (30 minutes)
+pub const CONNECT_CONFIRM_EXP: usize = 60 * 30;
+/// The login event expiration time (90 days)
+pub const LOGIN_EVENT_EXP: i64 = 60 * 60 * 24 * 90;
+/// The magic link expiration time (10 minutes)
+pub const MAGIC_LINK_EXP: usize = 60 * 10;
+/// The staff email code expiration time (1 day)
+pub const STAFF_EMAIL_EXP: usize = 60 * 60 * 24;
+/// The max size of the teacher verification document (5 MiB), far below the 16 MB document limit of MongoDB
+pub const MAX_DOCUMENT_SIZE: u64 = 5 * 1024 * 1024;
 
 #[derive(Serialize)]
 #[serde(crate = "rocket::serde")]
@@ -20,6 +55,80 @@ pub struct AuthUrl {
 #[serde(crate = "rocket::serde")]
 pub struct Token {
     pub(crate) token: String,
+    pub(crate) refresh_token: String,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct MfaToken {
+    pub(crate) mfa_token: String,
+}
+
+/// The login result, a [MfaToken] is responded if the user enabled two-factor authentication,
+/// a [LinkToken] is responded if the OAuth account needs to be confirmed before linking
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde", untagged)]
+pub enum LoginToken {
+    Token(Token),
+    MfaToken(MfaToken),
+    LinkToken(LinkToken),
+}
+
+/// The token to confirm linking the OAuth account to the existing user with the same email
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct LinkToken {
+    pub(crate) link_token: String,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct TotpSecret {
+    pub(crate) secret: String,
+    /// The `otpauth://` uri, used as the payload of the QR code
+    pub(crate) uri: String,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct RecoveryCodes {
+    pub(crate) recovery_codes: Vec<String>,
+}
+
+/// The OAuth callback of linking a account
+#[derive(FromForm)]
+pub struct ConnectData {
+    pub(crate) code: String,
+    pub(crate) state: String,
+}
+
+#[derive(FromForm)]
+pub struct ConfirmConnectData {
+    pub(crate) link_token: String,
+    pub(crate) password: String,
+}
+
+#[derive(FromForm)]
+pub struct LinkTokenData {
+    pub(crate) link_token: String,
+}
+
+#[derive(FromForm)]
+pub struct ConnectConfirmCodeData {
+    /// The code from the email
+    pub(crate) code: String,
+}
+
+#[derive(FromForm)]
+pub struct MfaLoginData {
+    pub(crate) mfa_token: String,
+    /// A TOTP code or a recovery code
+    pub(crate) code: String,
+}
+
+#[derive(FromForm)]
+pub struct TotpCodeData {
+    pub(crate) code: String,
 }
 
 #[derive(FromForm)]
@@ -28,6 +137,84 @@ pub struct LoginFromData {
     pub(crate) email: String,
 }
 
+#[derive(FromForm)]
+pub struct RefreshTokenData {
+    pub(crate) refresh_token: String,
+}
+
+#[derive(FromForm)]
+pub struct ForgotPasswordData {
+    pub(crate) email: String,
+}
+
+#[derive(FromForm)]
+pub struct ResetPasswordData {
+    pub(crate) code: String,
+    pub(crate) password: String,
+}
+
+#[derive(FromForm)]
+pub struct ChangePasswordData {
+    /// Required if the user has a password
+    pub(crate) password: Option<String>,
+    pub(crate) new_password: String,
+}
+
+#[derive(FromForm)]
+pub struct UnlockAccountData {
+    pub(crate) code: String,
+}
+
+#[derive(FromForm)]
+pub struct MagicLinkData {
+    pub(crate) email: String,
+}
+
+#[derive(FromForm)]
+pub struct MagicLinkLoginData {
+    /// The code from the magic link
+    pub(crate) code: String,
+}
+
+#[derive(FromForm)]
+pub struct DeleteUserData {
+    /// Required if the user has a password
+    pub(crate) password: Option<String>,
+}
+
+#[derive(FromForm)]
+pub struct CancelDeletionData {
+    pub(crate) code: String,
+}
+
+#[derive(FromForm)]
+pub struct ChangeEmailData {
+    pub(crate) email: String,
+    /// Required if the user has a password
+    pub(crate) password: Option<String>,
+}
+
+#[derive(FromForm)]
+pub struct ChangeEmailConfirmData {
+    pub(crate) code: String,
+}
+
+#[derive(FromForm)]
+pub struct TeacherVerificationData<'r> {
+    pub(crate) school: String,
+    /// The staff email given by the school, a confirmation code is sent to it
+    pub(crate) staff_email: Option<String>,
+    /// The supporting document, e.g. a teacher certificate
+    pub(crate) document: Option<TempFile<'r>>,
+    /// The note to the reviewers
+    pub(crate) note: Option<String>,
+}
+
+#[derive(FromForm)]
+pub struct StaffEmailConfirmData {
+    pub(crate) code: String,
+}
+
 #[derive(FromForm)]
 pub struct SignUp {
     pub(crate) username: String,
@@ -41,12 +228,84 @@ pub struct SignUp {
 pub struct Claims {
     /// Required (validate_exp defaults to true in validation). Expiration time (as UTC timestamp)
     pub(crate) exp: usize,
+    pub(crate) purpose: TokenPurpose,
+    /// Issued at (as UTC timestamp)
+    pub(crate) iat: usize,
+    /// The time (as UTC timestamp) when the user logged in, kept when the token is refreshed
+    pub(crate) auth_time: usize,
+    /// The unique id of the token
+    pub(crate) jti: String,
+    /// The session (refresh token family) id of the token
+    pub(crate) sid: String,
     pub(crate) username: String,
     pub(crate) verified_email: bool,
     pub(crate) id: String,
     pub(crate) modes: Vec<UserMode>,
 }
 
+purpose_claims!(Claims, Access);
+
+impl Claims {
+    /// Create the claims of a new access token, the `sid` is set when the session is created.
+    pub fn new(id: String, username: String, verified_email: bool, modes: Vec<UserMode>) -> Self {
+        Claims {
+            exp: create_exp(ACCESS_TOKEN_EXP),
+            purpose: TokenPurpose::Access,
+            iat: create_exp(0),
+            auth_time: create_exp(0),
+            jti: create_random_token(32),
+            sid: String::new(),
+            username,
+            verified_email,
+            id,
+            modes,
+        }
+    }
+}
+
+/// The claims of the token waiting for the two-factor authentication
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct MfaClaims {
+    pub(crate) exp: usize,
+    pub(crate) purpose: TokenPurpose,
+    pub(crate) id: String,
+    pub(crate) auth_time: usize,
+    /// The first factor, the login event is recorded after the second factor
+    pub(crate) method: LoginMethod,
+}
+
+purpose_claims!(MfaClaims, Mfa);
+
+/// The claims of the token waiting for the confirmation of linking the OAuth account
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct LinkClaims {
+    pub(crate) exp: usize,
+    pub(crate) purpose: TokenPurpose,
+    /// The id of the existing user
+    pub(crate) id: String,
+    pub(crate) connect: ConnectAccount,
+}
+
+purpose_claims!(LinkClaims, Link);
+
+/// The claims of the OAuth `state`, the callback must send back the same state
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct OAuthStateClaims {
+    pub(crate) exp: usize,
+    pub(crate) purpose: TokenPurpose,
+    /// A random value, each state is different
+    pub(crate) nonce: String,
+    pub(crate) account_type: ConnectType,
+    pub(crate) redirect_uri: String,
+    /// The login user of the link account request, [None] for the login
+    pub(crate) user_id: Option<String>,
+}
+
+purpose_claims!(OAuthStateClaims, OAuthState);
+
 #[doc(hidden)]
 #[derive(Serialize)]
 #[serde(crate = "rocket::serde")]
@@ -55,6 +314,10 @@ pub struct LoginUserData {
     pub(crate) username: String,
     pub(crate) verified_email: bool,
     pub(crate) modes: Vec<UserMode>,
+    pub(crate) jti: String,
+    pub(crate) sid: String,
+    pub(crate) exp: usize,
+    pub(crate) auth_time: usize,
 }
 
 #[derive(FromForm)]
@@ -103,18 +366,61 @@ impl<'r> FromRequest<'r> for LoginUserData {
 
         // get rocket config
         let config = request.guard::<&State<Config>>().await.succeeded().unwrap();
+        let db = request
+            .guard::<&State<Database>>()
+            .await
+            .succeeded()
+            .unwrap();
 
-        return if let Ok(user_data) =
+        let claims = if let Ok(user_data) =
             verify_token::<Claims>(token_content, config.public_key.as_bytes())
         {
-            Outcome::Success(LoginUserData {
-                id: user_data.claims.id,
-                username: user_data.claims.username,
-                modes: user_data.claims.modes,
-                verified_email: user_data.claims.verified_email,
-            })
+            user_data.claims
+        } else {
+            return LoginUserData::unauthorized();
+        };
+
+        let user_id = if let Ok(user_id) = ObjectId::parse_str(&claims.id) {
+            user_id
         } else {
-            LoginUserData::unauthorized()
+            return LoginUserData::unauthorized();
         };
+
+        // check the token is not revoked, the token can't be checked without the database
+        let revoked_token = match db.revoked_token.as_ref() {
+            Some(revoked_token) => revoked_token,
+            None => return LoginUserData::unauthorized(),
+        };
+
+        // `iat` is in seconds, so the tokens issued in the same second as the revocation are revoked too.
+        let revoked = revoked_token
+            .find_one(
+                doc! {
+                    "user_id": user_id,
+                    "$or": [
+                        { "jti": &claims.jti },
+                        { "sid": &claims.sid },
+                        { "issued_before": { "$gte": claims.iat as i64 } }
+                    ]
+                },
+                None,
+            )
+            .await
+            .unwrap();
+
+        if revoked.is_some() {
+            return LoginUserData::unauthorized();
+        }
+
+        Outcome::Success(LoginUserData {
+            id: claims.id,
+            username: claims.username,
+            modes: claims.modes,
+            verified_email: claims.verified_email,
+            jti: claims.jti,
+            sid: claims.sid,
+            exp: claims.exp,
+            auth_time: claims.auth_time,
+        })
     }
 }
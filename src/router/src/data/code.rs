@@ -35,6 +35,45 @@ impl Code {
         SignUpEmailAlreadyRegistered(5, "This email is already registered."),
         VerifyEmailError(6, "This code is invalid."),
         AuthError(7, "This token is invalid."),
-        EditUserFailed(8, "Edit the user info failed.")
+        EditUserFailed(8, "Edit the user info failed."),
+        RefreshTokenError(9, "This refresh token is invalid."),
+        RefreshTokenReused(10, "This refresh token has already been used."),
+        ResetPasswordError(11, "This reset password code is invalid."),
+        WeakPassword(12, "The password must have at least 8 characters, including letters and numbers."),
+        ReauthenticationRequired(13, "Please login again to continue."),
+        MfaRequired(14, "Two-factor authentication is required."),
+        MfaCodeError(15, "The two-factor authentication code is invalid."),
+        MfaTokenError(16, "This two-factor authentication token is invalid."),
+        MfaAlreadyEnabled(17, "Two-factor authentication is already enabled."),
+        PasskeyError(18, "The passkey is invalid."),
+        PasskeyStateError(19, "The passkey challenge is invalid or expired."),
+        PasskeyNotFound(20, "The passkey is not found."),
+        OAuthStateError(21, "The OAuth state is invalid or expired."),
+        ConnectAlreadyLinked(22, "This account is already linked to a user."),
+        ConnectNotFound(23, "The connected account is not found."),
+        LastLoginMethod(24, "The last login method of the user can't be removed."),
+        LinkRequired(25, "This email is already registered, please confirm the account to link it."),
+        LinkTokenError(26, "This link token is invalid."),
+        TooManyRequests(27, "Too many requests, please try again later."),
+        EmailAlreadyVerified(28, "The email is already verified."),
+        ChangeEmailError(29, "This change email code is invalid."),
+        MagicLinkError(30, "This login link is invalid or has already been used."),
+        AccountPendingDeletion(31, "This account is scheduled for deletion, use the link in the email to cancel it."),
+        CancelDeletionError(32, "This cancel deletion code is invalid."),
+        SessionNotFound(33, "The session is not found."),
+        LoginCredentialsError(34, "The email or the password is incorrect."),
+        LoginLocked(35, "Too many failed logins, please try again later or unlock the account by the email."),
+        UnlockAccountError(36, "This unlock account code is invalid."),
+        PermissionDenied(37, "The user doesn't have the role of this action."),
+        UserNotFound(38, "The user is not found."),
+        TeacherVerificationRequired(39, "The teacher mode requires an approved teacher verification."),
+        TeacherVerificationPending(40, "A teacher verification request is already pending."),
+        TeacherVerificationInvalid(41, "The school and a staff email or a document are required."),
+        AlreadyTeacher(42, "The user is already a teacher."),
+        TeacherVerificationNotFound(43, "The teacher verification request is not found."),
+        StaffEmailConfirmError(44, "This staff email code is invalid."),
+        AccountSuspended(45, "This account is suspended by the admin."),
+        DocumentTooLarge(46, "The document must not be larger than 5 MiB."),
+        DocumentUploadError(47, "The document can't be saved, please try again.")
     }
 }
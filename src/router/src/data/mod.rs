@@ -1,4 +1,7 @@
+pub mod admin_data;
 pub mod auth_data;
 pub mod code;
+pub mod passkey;
 pub mod response;
+pub mod role;
 pub mod user;
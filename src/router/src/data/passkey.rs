@@ -0,0 +1,132 @@
+use database::model::auth::login_event::LoginMethod;
+use rocket::serde::{Deserialize, Serialize};
+use util::jwt::TokenPurpose;
+use util::purpose_claims;
+
+/// The claims of the passkey ceremony state, the challenge is also stored by the server until it is used
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct PasskeyClaims {
+    pub(crate) exp: usize,
+    pub(crate) purpose: TokenPurpose,
+    pub(crate) challenge: String,
+    /// `register` or `login`
+    pub(crate) ceremony: String,
+    /// The user id, [`None`] for the passwordless login
+    pub(crate) id: Option<String>,
+    /// The login time of the two-factor authentication login
+    pub(crate) auth_time: Option<usize>,
+    /// The first factor of the two-factor authentication login
+    pub(crate) method: Option<LoginMethod>,
+}
+
+purpose_claims!(PasskeyClaims, Passkey);
+
+/// The options of the passkey ceremony
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct PasskeyOptions<T> {
+    /// The ceremony state, send it back with the passkey
+    pub(crate) state: String,
+    /// The `publicKey` option of `navigator.credentials.create()` or `navigator.credentials.get()`
+    pub(crate) public_key: T,
+}
+
+/// `PublicKeyCredentialCreationOptions`
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct PasskeyCreationOptions {
+    pub(crate) challenge: String,
+    pub(crate) rp: PasskeyRelyingParty,
+    pub(crate) user: PasskeyUser,
+    pub(crate) pub_key_cred_params: Vec<PasskeyCredentialParameter>,
+    pub(crate) timeout: usize,
+    pub(crate) exclude_credentials: Vec<PasskeyCredentialDescriptor>,
+    pub(crate) authenticator_selection: PasskeyAuthenticatorSelection,
+    pub(crate) attestation: &'static str,
+}
+
+/// `PublicKeyCredentialRequestOptions`
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct PasskeyRequestOptions {
+    pub(crate) challenge: String,
+    pub(crate) rp_id: String,
+    pub(crate) timeout: usize,
+    pub(crate) allow_credentials: Vec<PasskeyCredentialDescriptor>,
+    pub(crate) user_verification: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct PasskeyRelyingParty {
+    pub(crate) id: String,
+    pub(crate) name: String,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct PasskeyUser {
+    /// The base64url user handle
+    pub(crate) id: String,
+    pub(crate) name: String,
+    pub(crate) display_name: String,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct PasskeyCredentialParameter {
+    #[serde(rename = "type")]
+    pub(crate) credential_type: &'static str,
+    pub(crate) alg: i64,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct PasskeyCredentialDescriptor {
+    #[serde(rename = "type")]
+    pub(crate) credential_type: &'static str,
+    /// The base64url credential id
+    pub(crate) id: String,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+pub struct PasskeyAuthenticatorSelection {
+    pub(crate) resident_key: &'static str,
+    pub(crate) user_verification: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct PasskeyInfo {
+    pub(crate) credential_id: String,
+    pub(crate) name: String,
+    /// The created time (as UTC timestamp in milliseconds)
+    pub(crate) created_at: i64,
+}
+
+/// The result of `navigator.credentials.create()`, the binary fields are base64url
+#[derive(FromForm)]
+pub struct PasskeyRegisterData {
+    pub(crate) state: String,
+    pub(crate) client_data_json: String,
+    pub(crate) attestation_object: String,
+    pub(crate) name: Option<String>,
+}
+
+#[derive(FromForm)]
+pub struct PasskeyLoginOptionsData {
+    /// Use the passkey as the second factor of the login
+    pub(crate) mfa_token: Option<String>,
+}
+
+/// The result of `navigator.credentials.get()`, the binary fields are base64url
+#[derive(FromForm)]
+pub struct PasskeyLoginData {
+    pub(crate) state: String,
+    pub(crate) credential_id: String,
+    pub(crate) client_data_json: String,
+    pub(crate) authenticator_data: String,
+    pub(crate) signature: String,
+}
@@ -0,0 +1,120 @@
+use std::marker::PhantomData;
+
+use crate::data::auth_data::LoginUserData;
+use crate::data::code::Code;
+use crate::data::response::Response;
+use database::model::auth::user::{Role, User};
+use database::mongodb::bson::oid::ObjectId;
+use database::{doc, Database};
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome};
+use rocket::serde::json::Json;
+use rocket::{Request, State};
+use util::role::roles_include;
+
+/// A role which can be required by [RequireRole]
+pub trait RoleType: Send + Sync {
+    const ROLE: Role;
+}
+
+/// The [Role::Admin] role
+pub enum Admin {}
+/// The [Role::SchoolAdmin] role
+pub enum SchoolAdmin {}
+
+impl RoleType for Admin {
+    const ROLE: Role = Role::Admin;
+}
+impl RoleType for SchoolAdmin {
+    const ROLE: Role = Role::SchoolAdmin;
+}
+
+pub type RoleError = (Status, Json<Response<String>>);
+
+/// The login user who has the role `R`, or a role with all the permissions of `R`
+///
+/// The roles are read from the database, so a removed role or a suspension takes effect immediately.
+/// # Example
+/// ```rust,ignore
+/// #[get("/teacher-verifications")]
+/// async fn teacher_verifications(reviewer: Result<RequireRole<SchoolAdmin>, RoleError>) -> ...
+/// ```
+#[doc(hidden)]
+pub struct RequireRole<R: RoleType> {
+    /// The login user
+    pub(crate) user: User,
+    role: PhantomData<R>,
+}
+
+#[rocket::async_trait]
+#[doc(hidden)]
+impl<'r, R: RoleType> FromRequest<'r> for RequireRole<R> {
+    type Error = RoleError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let login_user_data = match request.guard::<LoginUserData>().await {
+            Outcome::Success(login_user_data) => login_user_data,
+            Outcome::Failure((status, err)) => {
+                return Outcome::Failure((status, (status, err.0.unwrap())));
+            }
+            Outcome::Forward(()) => return Outcome::Forward(()),
+        };
+
+        let db = request
+            .guard::<&State<Database>>()
+            .await
+            .succeeded()
+            .unwrap();
+
+        let user = db
+            .user
+            .as_ref()
+            .unwrap()
+            .find_one(
+                doc! {
+                    "_id": ObjectId::parse_str(&login_user_data.id).unwrap()
+                },
+                None,
+            )
+            .await
+            .unwrap();
+
+        let user = match user {
+            Some(user) => user,
+            None => {
+                return Outcome::Failure((
+                    Status::Unauthorized,
+                    (
+                        Status::Unauthorized,
+                        Response::new(Code::LoginUserNotFoundError, None),
+                    ),
+                ));
+            }
+        };
+
+        if user.suspended_at.is_some() {
+            return Outcome::Failure((
+                Status::Forbidden,
+                (
+                    Status::Forbidden,
+                    Response::new(Code::AccountSuspended, None),
+                ),
+            ));
+        }
+
+        if !roles_include(&user.roles, R::ROLE) {
+            return Outcome::Failure((
+                Status::Forbidden,
+                (
+                    Status::Forbidden,
+                    Response::new(Code::PermissionDenied, None),
+                ),
+            ));
+        }
+
+        Outcome::Success(RequireRole {
+            user,
+            role: PhantomData,
+        })
+    }
+}
@@ -1,4 +1,8 @@
-use database::model::auth::user::{ConnectAccount, UserMode};
+use database::model::auth::login_event::{LoginEvent, LoginMethod};
+use database::model::auth::session::Session;
+use database::model::auth::user::{ConnectAccount, ConnectType, Role, User, UserMode};
+use database::model::teacher_verification::{TeacherVerification, VerificationStatus};
+use database::mongodb::bson::DateTime;
 use rocket::serde::Serialize;
 
 #[derive(Serialize)]
@@ -7,5 +11,233 @@ pub struct UserInfo {
     pub(crate) username: String,
     pub(crate) email: String,
     pub(crate) modes: Vec<UserMode>,
-    pub(crate) connects: Vec<ConnectAccount>,
+    pub(crate) roles: Vec<Role>,
+    pub(crate) connects: Vec<ConnectInfo>,
+    /// The avatar of the last login connected account
+    pub(crate) avatar: Option<String>,
+}
+
+impl From<User> for UserInfo {
+    fn from(user: User) -> Self {
+        let avatar = user
+            .connects
+            .iter()
+            .filter(|connect| connect.avatar.is_some())
+            .max_by_key(|connect| connect.last_login)
+            .and_then(|connect| connect.avatar.clone());
+
+        UserInfo {
+            username: user.username,
+            email: user.email,
+            modes: user.modes,
+            roles: user.roles,
+            connects: user.connects.into_iter().map(ConnectInfo::from).collect(),
+            avatar,
+        }
+    }
+}
+
+/// A connected account of the user
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ConnectInfo {
+    pub(crate) account_type: ConnectType,
+    pub(crate) subject: Option<String>,
+    pub(crate) name: String,
+    pub(crate) email: String,
+    pub(crate) avatar: Option<String>,
+    /// The last login time (as UTC timestamp in milliseconds)
+    pub(crate) last_login: Option<i64>,
+    /// The school id, only of the Taiwan Cloud Education account
+    pub(crate) school_id: Option<String>,
+    /// The grade, only of the Taiwan Cloud Education account
+    pub(crate) grade: Option<String>,
+}
+
+impl From<ConnectAccount> for ConnectInfo {
+    fn from(connect: ConnectAccount) -> Self {
+        ConnectInfo {
+            account_type: connect.account_type,
+            subject: connect.subject,
+            name: connect.name,
+            email: connect.email,
+            avatar: connect.avatar,
+            last_login: connect
+                .last_login
+                .map(|last_login| last_login.timestamp_millis()),
+            school_id: connect.school_id,
+            grade: connect.grade,
+        }
+    }
+}
+
+/// All the personal data of the user, the secrets (e.g. the password hash) are not included
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct UserExport {
+    pub(crate) id: String,
+    pub(crate) username: String,
+    pub(crate) email: String,
+    pub(crate) verified_email: bool,
+    pub(crate) modes: Vec<UserMode>,
+    /// The login ips recorded before the login events
+    pub(crate) login_ips: Vec<String>,
+    pub(crate) roles: Vec<Role>,
+    pub(crate) connects: Vec<ConnectInfo>,
+    pub(crate) passkeys: Vec<PasskeyExport>,
+    pub(crate) has_password: bool,
+    pub(crate) mfa_enabled: bool,
+    /// The deletion time (as UTC timestamp in milliseconds)
+    pub(crate) delete_at: Option<i64>,
+    pub(crate) sessions: Vec<SessionInfo>,
+    pub(crate) login_events: Vec<LoginEventInfo>,
+    pub(crate) teacher_verifications: Vec<TeacherVerificationInfo>,
+    /// The export time (as UTC timestamp in milliseconds)
+    pub(crate) exported_at: i64,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct PasskeyExport {
+    pub(crate) name: String,
+    /// The created time (as UTC timestamp in milliseconds)
+    pub(crate) created_at: i64,
+}
+
+/// A login session of the user
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct SessionInfo {
+    pub(crate) id: String,
+    /// The readable device name, e.g. `Chrome on ChromeOS`
+    pub(crate) device_name: String,
+    pub(crate) ip: String,
+    pub(crate) user_agent: Option<String>,
+    /// The login time (as UTC timestamp in milliseconds)
+    pub(crate) created_at: i64,
+    /// The last refresh time (as UTC timestamp in milliseconds)
+    pub(crate) last_seen: i64,
+    /// The session of the current login token
+    pub(crate) current: bool,
+}
+
+impl SessionInfo {
+    pub(crate) fn new(session: Session, current_sid: &str) -> Self {
+        let id = session._id.to_hex();
+
+        SessionInfo {
+            current: id == current_sid,
+            id,
+            device_name: session.device_name,
+            ip: session.ip,
+            user_agent: session.user_agent,
+            created_at: session.created_at.timestamp_millis(),
+            last_seen: session.last_seen.timestamp_millis(),
+        }
+    }
+}
+
+impl UserExport {
+    pub(crate) fn new(
+        user: User,
+        sessions: Vec<Session>,
+        login_events: Vec<LoginEvent>,
+        teacher_verifications: Vec<TeacherVerification>,
+    ) -> Self {
+        UserExport {
+            id: user._id.to_string(),
+            username: user.username,
+            email: user.email,
+            verified_email: user.verified_email,
+            modes: user.modes,
+            login_ips: user.login_ips,
+            roles: user.roles,
+            connects: user.connects.into_iter().map(ConnectInfo::from).collect(),
+            passkeys: user
+                .passkeys
+                .into_iter()
+                .map(|passkey| PasskeyExport {
+                    name: passkey.name,
+                    created_at: passkey.created_at.timestamp_millis(),
+                })
+                .collect(),
+            has_password: user.password_hash.is_some(),
+            mfa_enabled: user.totp_secret.is_some(),
+            delete_at: user.delete_at.map(|delete_at| delete_at.timestamp_millis()),
+            sessions: sessions
+                .into_iter()
+                .map(|session| SessionInfo::new(session, ""))
+                .collect(),
+            login_events: login_events.into_iter().map(LoginEventInfo::from).collect(),
+            teacher_verifications: teacher_verifications
+                .into_iter()
+                .map(TeacherVerificationInfo::from)
+                .collect(),
+            exported_at: DateTime::now().timestamp_millis(),
+        }
+    }
+}
+
+/// A login attempt of the user
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct LoginEventInfo {
+    /// The login time (as UTC timestamp in milliseconds)
+    pub(crate) time: i64,
+    pub(crate) ip: String,
+    pub(crate) user_agent: Option<String>,
+    pub(crate) method: LoginMethod,
+    pub(crate) success: bool,
+}
+
+impl From<LoginEvent> for LoginEventInfo {
+    fn from(login_event: LoginEvent) -> Self {
+        LoginEventInfo {
+            time: login_event.time.timestamp_millis(),
+            ip: login_event.ip,
+            user_agent: login_event.user_agent,
+            method: login_event.method,
+            success: login_event.success,
+        }
+    }
+}
+
+/// A teacher verification request, the document is downloaded separately
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct TeacherVerificationInfo {
+    pub(crate) id: String,
+    pub(crate) user_id: String,
+    pub(crate) school: String,
+    pub(crate) staff_email: Option<String>,
+    pub(crate) staff_email_verified: bool,
+    /// The file name of the uploaded document
+    pub(crate) document_name: Option<String>,
+    pub(crate) note: Option<String>,
+    pub(crate) status: VerificationStatus,
+    /// The created time (as UTC timestamp in milliseconds)
+    pub(crate) created_at: i64,
+    pub(crate) review_note: Option<String>,
+    /// The review time (as UTC timestamp in milliseconds)
+    pub(crate) reviewed_at: Option<i64>,
+}
+
+impl From<TeacherVerification> for TeacherVerificationInfo {
+    fn from(verification: TeacherVerification) -> Self {
+        TeacherVerificationInfo {
+            id: verification._id.to_hex(),
+            user_id: verification.user_id.to_hex(),
+            school: verification.school,
+            staff_email: verification.staff_email,
+            staff_email_verified: verification.staff_email_verified,
+            document_name: verification.document.map(|document| document.name),
+            note: verification.note,
+            status: verification.status,
+            created_at: verification.created_at.timestamp_millis(),
+            review_note: verification.review_note,
+            reviewed_at: verification
+                .reviewed_at
+                .map(|reviewed_at| reviewed_at.timestamp_millis()),
+        }
+    }
 }
@@ -1,11 +1,37 @@
 use crate::Config;
-use database::Error;
+use database::model::auth::user::Role;
+use database::mongodb::bson;
+use database::{doc, Error};
 use rocket::{Build, Rocket};
 
 pub async fn db_init(rocket: Rocket<Build>, config: Config) -> Result<Rocket<Build>, Error> {
-    match database::init(config.mongodb_url).await {
+    match database::init(config.mongodb_url.clone()).await {
         Ok(db) => {
             info!("Connected successfully.");
+
+            // Changing the email relies on the unique email index, so the server can't start without it.
+            let duplicate_emails = database::duplicate_emails(db.user.as_ref().unwrap()).await?;
+            if !duplicate_emails.is_empty() {
+                panic!(
+                    "The emails {:?} are used by more than one user, merge the users to create the unique email index.",
+                    duplicate_emails
+                );
+            }
+
+            // Grant the admin role to the users in the config.
+            db.user
+                .as_ref()
+                .unwrap()
+                .update_many(
+                    doc! {
+                        "email": { "$in": &config.admin_emails },
+                        "verified_email": true
+                    },
+                    doc! { "$addToSet": { "roles": bson::to_bson(&Role::Admin).unwrap() } },
+                    None,
+                )
+                .await?;
+
             Ok(rocket.manage(db))
         }
         Err(err) => Err(err),
@@ -7,12 +7,14 @@ use rocket::http::Method;
 use rocket::serde::Deserialize;
 use rocket::{Build, Rocket};
 use rocket_cors::{AllowedOrigins, CorsOptions};
+use std::collections::HashMap;
 
 mod apis;
 mod catch;
 mod data;
 #[doc(hidden)]
 mod db;
+mod rate_limit;
 mod resource;
 
 #[derive(Deserialize)]
@@ -23,16 +25,47 @@ pub struct Config {
     public_key: String,
     mongodb_url: String,
 
-    google_oauth_id: String,
-    google_oauth_secret: String,
     google_account_email: String,
     google_account_password: String,
 
-    facebook_oauth_id: String,
-    facebook_oauth_secret: String,
+    /// The OAuth clients, the key is the name of the provider
+    oauth: HashMap<String, OAuthConfig>,
     allowed_origins: Vec<String>,
+    webauthn_rp_id: String,
+    webauthn_rp_name: String,
 
     issuer: String,
+    reset_password_url: String,
+    connect_confirm_url: String,
+    change_email_url: String,
+    magic_link_url: String,
+    cancel_deletion_url: String,
+    unlock_account_url: String,
+    staff_email_confirm_url: String,
+    /// The days before the user requested deletion is purged
+    account_deletion_grace_days: i64,
+    /// The front-end pages redirected to after verifying the email
+    verify_email_success_url: String,
+    verify_email_failure_url: String,
+
+    /// The users of the emails are granted the admin role when the server is started,
+    /// the email must be verified
+    #[serde(default)]
+    admin_emails: Vec<String>,
+
+    /// The rate limit rules, the first matched rule is used
+    #[serde(default)]
+    rate_limits: Vec<rate_limit::RateLimitRule>,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+#[doc(hidden)]
+pub struct OAuthConfig {
+    client_id: String,
+    client_secret: String,
+    /// The issuer url of a OpenID Connect provider which is not built-in
+    issuer: Option<String>,
 }
 
 /// rocket server
@@ -49,7 +82,16 @@ pub async fn rocket(test: bool) -> Rocket<Build> {
     } else {
         rocket.manage(database::Database {
             client: None,
+            audit_log: None,
             user: None,
+            refresh_token: None,
+            revoked_token: None,
+            data_export: None,
+            login_events: None,
+            session: None,
+            login_failure: None,
+            passkey_challenge: None,
+            teacher_verification: None,
         })
     }
 }
@@ -61,6 +103,7 @@ pub fn stage() -> AdHoc {
             .attach(AdHoc::config::<Config>())
             .attach(catch::stage())
             .attach(resource::stage())
+            .attach(rate_limit::stage())
             .attach(apis::stage())
     })
 }
@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use rocket::fairing::{AdHoc, Fairing, Info, Kind};
+use rocket::http::uri::Origin;
+use rocket::http::{Header, Method};
+use rocket::request::{FromRequest, Outcome};
+use rocket::serde::json::Json;
+use rocket::serde::Deserialize;
+use rocket::{Data, Request};
+use util::jwt::verify_token;
+use util::rate_limit::{path_matches, TokenBucket};
+
+use crate::data::auth_data::Claims;
+use crate::data::code::Code;
+use crate::data::response::Response;
+use crate::Config;
+
+/// The path which the limited requests are rewritten to
+const RATE_LIMITED_PATH: &str = "/rate-limited";
+/// The maximum number of the buckets, the full buckets and then the least recently used buckets are dropped
+const MAX_BUCKETS: usize = 10000;
+
+/// A rate limit rule in the config
+#[derive(Deserialize, Clone)]
+#[serde(crate = "rocket::serde")]
+#[doc(hidden)]
+pub struct RateLimitRule {
+    /// The request method, any method if it is [`None`]
+    method: Option<String>,
+    /// The request path, a path ending with `*` matches the prefix
+    path: String,
+    /// The maximum requests in a burst
+    capacity: u32,
+    /// The seconds to refill the full bucket
+    period: u64,
+}
+
+impl RateLimitRule {
+    fn matches(&self, method: Method, path: &str) -> bool {
+        let method_matches = self.method.as_ref().map_or(true, |rule_method| {
+            rule_method.eq_ignore_ascii_case(method.as_str())
+        });
+
+        method_matches && path_matches(&self.path, path)
+    }
+
+    /// The tokens refilled per second
+    fn rate(&self) -> f64 {
+        self.capacity as f64 / self.period.max(1) as f64
+    }
+}
+
+/// The seconds before the limited request can be retried
+struct RetryAfter(u64);
+
+/// A token bucket rate limiter of the rules in the config,
+/// keyed by the login user or the ip of the request without a login token
+pub struct RateLimiter {
+    rules: Vec<RateLimitRule>,
+    public_key: String,
+    buckets: Mutex<HashMap<(usize, String), TokenBucket>>,
+}
+
+impl RateLimiter {
+    fn new(rules: Vec<RateLimitRule>, public_key: String) -> Self {
+        RateLimiter {
+            rules,
+            public_key,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The login user id of the token, the token is only verified by the signature
+    fn user_id(&self, request: &Request<'_>) -> Option<String> {
+        let token = request
+            .headers()
+            .get_one("Authorization")?
+            .strip_prefix("Bearer ")?;
+
+        verify_token::<Claims>(token.to_string(), self.public_key.as_bytes())
+            .ok()
+            .map(|token_data| token_data.claims.id)
+    }
+
+    /// Take a token from the bucket of the rule
+    ///
+    /// return the seconds to wait if the bucket is empty
+    fn take(&self, rule_index: usize, key: String) -> Result<(), u64> {
+        let rule = &self.rules[rule_index];
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+
+        if buckets.len() >= MAX_BUCKETS {
+            // The full buckets are the same as the new buckets.
+            buckets.retain(|(index, _), bucket| {
+                let rule = &self.rules[*index];
+                bucket.tokens_at(rule.capacity, rule.rate(), now) < rule.capacity as f64
+            });
+        }
+        if buckets.len() >= MAX_BUCKETS {
+            // Too many clients are limited at the same time, e.g. a flood of ips,
+            // keep the recently used half of the buckets so the memory is bounded.
+            let mut updated = buckets
+                .values()
+                .map(|bucket| bucket.updated())
+                .collect::<Vec<_>>();
+            updated.sort_unstable();
+            let oldest_kept = updated[updated.len() / 2];
+            buckets.retain(|_, bucket| bucket.updated() >= oldest_kept);
+        }
+
+        buckets
+            .entry((rule_index, key))
+            .or_insert_with(|| TokenBucket::new(rule.capacity, now))
+            .take(rule.capacity, rule.rate(), now)
+    }
+}
+
+#[rocket::async_trait]
+impl Fairing for RateLimiter {
+    fn info(&self) -> Info {
+        Info {
+            name: "Rate limiter",
+            kind: Kind::Request,
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _: &mut Data<'_>) {
+        let path = request.uri().path().to_string();
+        let rule_index = match self
+            .rules
+            .iter()
+            .position(|rule| rule.matches(request.method(), &path))
+        {
+            Some(rule_index) => rule_index,
+            None => return,
+        };
+
+        let key = match self.user_id(request) {
+            Some(user_id) => format!("user:{}", user_id),
+            None => format!(
+                "ip:{}",
+                request
+                    .client_ip()
+                    .map(|ip| ip.to_string())
+                    .unwrap_or_default()
+            ),
+        };
+
+        if let Err(retry_after) = self.take(rule_index, key) {
+            request.local_cache(|| Some(RetryAfter(retry_after)));
+            request.set_method(Method::Get);
+            request.set_uri(Origin::parse(RATE_LIMITED_PATH).unwrap());
+        }
+    }
+}
+
+/// The request limited by the [RateLimiter]
+struct RateLimited(u64);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for RateLimited {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match request.local_cache(|| None::<RetryAfter>) {
+            Some(RetryAfter(retry_after)) => Outcome::Success(RateLimited(*retry_after)),
+            None => Outcome::Forward(()),
+        }
+    }
+}
+
+#[derive(Responder)]
+#[response(status = 429)]
+struct RateLimitedResponse(Json<Response<String>>, Header<'static>);
+
+/// The response of the limited requests, with the `Retry-After` header
+#[get("/rate-limited")]
+fn rate_limited(rate_limited: RateLimited) -> RateLimitedResponse {
+    RateLimitedResponse(
+        Response::new(Code::TooManyRequests, None),
+        Header::new("Retry-After", rate_limited.0.to_string()),
+    )
+}
+
+#[doc(hidden)]
+pub fn stage() -> AdHoc {
+    AdHoc::on_ignite("load rate limit stage", |rocket| async {
+        let config: Config = rocket.figment().extract().expect("config");
+
+        rocket
+            .attach(RateLimiter::new(config.rate_limits, config.public_key))
+            .mount("/", routes![rate_limited])
+    })
+}
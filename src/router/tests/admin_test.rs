@@ -0,0 +1,22 @@
+use rocket::http::{ContentType, Status};
+use rocket::local::asynchronous::Client;
+
+#[rocket::async_test]
+async fn require_role_without_token() {
+    let client = Client::tracked(router::rocket(true).await)
+        .await
+        .expect("valid rocket instance");
+    let req = client
+        .put("/admin/users/000000000000000000000000/roles")
+        .header(ContentType::Form)
+        .body(r#"roles=["Teacher"]"#);
+    let response = rocket::tokio::join!(req.clone().dispatch());
+
+    assert_eq!(response.0.status(), Status::Unauthorized);
+    assert!(response
+        .0
+        .into_string()
+        .await
+        .unwrap()
+        .starts_with(r#"{"code":7,"#));
+}
@@ -6,15 +6,16 @@ async fn google_oauth_url() {
     let client = Client::tracked(router::rocket(true).await)
         .await
         .expect("valid rocket instance");
-    let req = client
-        .get("/authentication/google/url?redirect_uri=http://127.0.0.1/login/oauth/google");
+    let req =
+        client.get("/authentication/google/url?redirect_uri=http://127.0.0.1/login/oauth/google");
     let response = rocket::tokio::join!(req.clone().dispatch());
 
     assert_eq!(response.0.status(), Status::Ok);
-    assert_eq!(
-        response.0.into_string().await.unwrap(),
-        r#"{"code":200,"message":"Ok.","data":{"url":"https://accounts.google.com/o/oauth2/auth?client_id=&response_type=code&scope=https%3A%2F%2Fwww.googleapis.com%2Fauth%2Fuserinfo.profile%20https%3A%2F%2Fwww.googleapis.com%2Fauth%2Fuserinfo.email&redirect_uri=http%3A%2F%2F127.0.0.1%2Flogin%2Foauth%2Fgoogle"}}"#
-    );
+    let body = response.0.into_string().await.unwrap();
+    assert!(body.starts_with(
+        r#"{"code":200,"message":"Ok.","data":{"url":"https://accounts.google.com/o/oauth2/auth?client_id=&response_type=code&scope=https%3A%2F%2Fwww.googleapis.com%2Fauth%2Fuserinfo.profile%20https%3A%2F%2Fwww.googleapis.com%2Fauth%2Fuserinfo.email&redirect_uri=http%3A%2F%2F127.0.0.1%2Flogin%2Foauth%2Fgoogle&state="#
+    ));
+    assert!(body.ends_with(r#"&code_challenge_method=S256"}}"#));
 }
 
 #[rocket::async_test]
@@ -27,8 +28,58 @@ async fn facebook_oauth_url() {
     let response = rocket::tokio::join!(req.clone().dispatch());
 
     assert_eq!(response.0.status(), Status::Ok);
+    let body = response.0.into_string().await.unwrap();
+    assert!(body.starts_with(
+        r#"{"code":200,"message":"Ok.","data":{"url":"https://www.facebook.com/dialog/oauth?client_id=&response_type=code&scope=public_profile%2Cemail&redirect_uri=http%3A%2F%2F127.0.0.1%2Flogin%2Foauth%2Ffacebook&state="#
+    ));
+    assert!(body.ends_with(r#"&code_challenge_method=S256"}}"#));
+}
+
+#[rocket::async_test]
+async fn tce_oauth_url() {
+    let client = Client::tracked(router::rocket(true).await)
+        .await
+        .expect("valid rocket instance");
+    let req = client.get("/authentication/tce/url?redirect_uri=http://127.0.0.1/login/oauth/tce");
+    let response = rocket::tokio::join!(req.clone().dispatch());
+
+    assert_eq!(response.0.status(), Status::Ok);
+    let body = response.0.into_string().await.unwrap();
+    assert!(body.starts_with(
+        r#"{"code":200,"message":"Ok.","data":{"url":"https://oidc.tanet.edu.tw/oidc/v1/azp?client_id=&response_type=code&scope=openid%20email%20profile%20eduinfo&redirect_uri=http%3A%2F%2F127.0.0.1%2Flogin%2Foauth%2Ftce&state="#
+    ));
+    assert!(body.ends_with(r#"&code_challenge_method=S256"}}"#));
+}
+
+#[rocket::async_test]
+async fn google_oauth_invalid_state() {
+    let client = Client::tracked(router::rocket(true).await)
+        .await
+        .expect("valid rocket instance");
+    let req = client
+        .get("/authentication/google?code=abc&state=xyz")
+        .remote("127.0.0.1:8000".parse().unwrap());
+    let response = rocket::tokio::join!(req.clone().dispatch());
+
+    assert_eq!(response.0.status(), Status::BadRequest);
+    assert_eq!(
+        response.0.into_string().await.unwrap(),
+        r#"{"code":21,"message":"The OAuth state is invalid or expired."}"#
+    );
+}
+
+#[rocket::async_test]
+async fn unknown_oauth_provider() {
+    let client = Client::tracked(router::rocket(true).await)
+        .await
+        .expect("valid rocket instance");
+    let req =
+        client.get("/authentication/github/url?redirect_uri=http://127.0.0.1/login/oauth/github");
+    let response = rocket::tokio::join!(req.clone().dispatch());
+
+    assert_eq!(response.0.status(), Status::NotFound);
     assert_eq!(
         response.0.into_string().await.unwrap(),
-        r#"{"code":200,"message":"Ok.","data":{"url":"https://www.facebook.com/dialog/oauth?client_id=&response_type=code&scope=public_profile%2Cemail&redirect_uri=http%3A%2F%2F127.0.0.1%2Flogin%2Foauth%2Ffacebook"}}"#
+        r#"{"code":404,"message":"Resource not found."}"#
     );
 }
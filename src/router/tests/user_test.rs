@@ -0,0 +1,261 @@
+use database::model::auth::user::UserMode;
+use database::mongodb::bson::{oid::ObjectId, Document};
+use database::{doc, Database};
+use rocket::http::{ContentType, Header, Status};
+use rocket::local::asynchronous::Client;
+use rocket::serde::json::Value;
+use util::bcrypt::password_hash;
+use util::email::ChangeEmailClaims;
+use util::jwt::{create_jwt_token, TokenPurpose};
+use util::util::{create_exp, create_random_token};
+
+#[rocket::async_test]
+async fn logout_without_token() {
+    let client = Client::tracked(router::rocket(true).await)
+        .await
+        .expect("valid rocket instance");
+    let req = client.post("/user/logout");
+    let response = rocket::tokio::join!(req.clone().dispatch());
+
+    assert_eq!(response.0.status(), Status::Unauthorized);
+    assert_eq!(
+        response.0.into_string().await.unwrap(),
+        r#"{"code":7,"message":"This token is invalid."}"#
+    );
+}
+
+#[rocket::async_test]
+async fn verify_email_rejects_change_email_code() {
+    let client = Client::tracked(router::rocket(true).await)
+        .await
+        .expect("valid rocket instance");
+    let private_key: String = client
+        .rocket()
+        .figment()
+        .extract_inner("private_key")
+        .unwrap();
+    // The change email code is sent to the new email chosen by the user.
+    let code = create_jwt_token(
+        private_key.as_bytes(),
+        ChangeEmailClaims {
+            exp: create_exp(60 * 5),
+            purpose: TokenPurpose::ChangeEmail,
+            id: "000000000000000000000000".to_string(),
+            email: "victim@lipoic.org".to_string(),
+            new_email: "attacker@lipoic.org".to_string(),
+        },
+    )
+    .unwrap();
+    let req = client.get(format!("/verify-email?code={}", code));
+    let response = rocket::tokio::join!(req.clone().dispatch());
+
+    assert_eq!(response.0.status(), Status::SeeOther);
+    assert_eq!(
+        response.0.headers().get_one("Location"),
+        Some("http://localhost:3000/verify-email/failure?error=6")
+    );
+}
+
+#[rocket::async_test]
+async fn login_rate_limited() {
+    let client = Client::tracked(router::rocket(true).await)
+        .await
+        .expect("valid rocket instance");
+
+    for _ in 0..30 {
+        let req = client
+            .post("/user/login/unlock")
+            .header(ContentType::Form)
+            .body("code=invalid");
+        let response = rocket::tokio::join!(req.clone().dispatch());
+
+        if response.0.status() == Status::TooManyRequests {
+            assert!(response.0.headers().get_one("Retry-After").is_some());
+            assert!(response
+                .0
+                .into_string()
+                .await
+                .unwrap()
+                .starts_with(r#"{"code":27,"#));
+            return;
+        }
+    }
+
+    panic!("the login requests are not rate limited");
+}
+
+#[rocket::async_test]
+#[ignore = "requires the MongoDB and the email account of Rocket.toml"]
+async fn sign_up_sends_verify_email() {
+    let client = Client::tracked(router::rocket(false).await)
+        .await
+        .expect("valid rocket instance");
+    let user = client
+        .rocket()
+        .state::<Database>()
+        .unwrap()
+        .user
+        .as_ref()
+        .unwrap();
+    let email = format!("{}@lipoic.org", create_random_token(16).to_lowercase());
+    let body = format!(
+        "username=test&email={}&password=password1234&modes=%5B%22Student%22%5D",
+        email
+    );
+
+    let response = client
+        .post("/user/sign-up")
+        .header(ContentType::Form)
+        .body(&body)
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let user_data = user
+        .find_one(doc! { "email": &email }, None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(user_data.modes, vec![UserMode::Student]);
+    // The verification email is sent to the new user.
+    assert!(user_data.verify_email_sent_at.is_some());
+
+    let response = client
+        .post("/user/sign-up")
+        .header(ContentType::Form)
+        .body(&body)
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Conflict);
+    assert!(response
+        .into_string()
+        .await
+        .unwrap()
+        .starts_with(r#"{"code":5,"#));
+
+    user.delete_one(doc! { "_id": user_data._id }, None)
+        .await
+        .unwrap();
+}
+
+#[rocket::async_test]
+#[ignore = "requires the MongoDB of Rocket.toml"]
+async fn link_and_unlink_connect() {
+    let client = Client::tracked(router::rocket(false).await)
+        .await
+        .expect("valid rocket instance");
+    let user = client
+        .rocket()
+        .state::<Database>()
+        .unwrap()
+        .user
+        .as_ref()
+        .unwrap();
+    let id = ObjectId::new();
+    let email = format!("{}@lipoic.org", create_random_token(16).to_lowercase());
+    let hash = password_hash(&"password1234".to_string()).unwrap();
+
+    user.clone_with_type::<Document>()
+        .insert_one(
+            doc! {
+                "_id": id,
+                "username": "test",
+                "email": &email,
+                "verified_email": true,
+                "password_hash": &hash,
+                "connects": [{
+                    "account_type": "Google",
+                    "subject": "google-subject",
+                    "name": "test",
+                    "email": &email
+                }],
+                "modes": ["Student"]
+            },
+            None,
+        )
+        .await
+        .unwrap();
+
+    let response = client
+        .post("/user/login")
+        .header(ContentType::Form)
+        .body(format!("email={}&password=password1234", email))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    let token = response.into_json::<Value>().await.unwrap()["data"]["token"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let authorization = || Header::new("Authorization", format!("Bearer {}", token));
+
+    // The OAuth state must be created for the login user.
+    let response = client
+        .post("/user/connects/facebook")
+        .header(ContentType::Form)
+        .header(authorization())
+        .body("code=code&state=invalid")
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Unauthorized);
+    assert!(response
+        .into_string()
+        .await
+        .unwrap()
+        .starts_with(r#"{"code":21,"#));
+
+    let response = client
+        .delete("/user/connects/facebook")
+        .header(authorization())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::NotFound);
+    assert!(response
+        .into_string()
+        .await
+        .unwrap()
+        .starts_with(r#"{"code":23,"#));
+
+    // The connected account is the last login method without the password.
+    user.update_one(
+        doc! { "_id": id },
+        doc! { "$unset": { "password_hash": "" } },
+        None,
+    )
+    .await
+    .unwrap();
+    let response = client
+        .delete("/user/connects/google")
+        .header(authorization())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Conflict);
+    assert!(response
+        .into_string()
+        .await
+        .unwrap()
+        .starts_with(r#"{"code":24,"#));
+
+    user.update_one(
+        doc! { "_id": id },
+        doc! { "$set": { "password_hash": &hash } },
+        None,
+    )
+    .await
+    .unwrap();
+    let response = client
+        .delete("/user/connects/google")
+        .header(authorization())
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    assert!(user
+        .find_one(doc! { "_id": id }, None)
+        .await
+        .unwrap()
+        .unwrap()
+        .connects
+        .is_empty());
+
+    user.delete_one(doc! { "_id": id }, None).await.unwrap();
+}
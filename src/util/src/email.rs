@@ -1,17 +1,105 @@
+use crate::jwt::TokenPurpose;
+use crate::purpose_claims;
+use database::model::auth::user::ConnectAccount;
 use lettre::transport::smtp::authentication::Credentials;
 use lettre::{
     message::{header, MultiPart, SinglePart},
     Message, SmtpTransport, Transport,
 };
-use maud::html;
+use maud::{html, Markup};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct VerifyEmailClaims {
     pub exp: usize,
+    pub purpose: TokenPurpose,
     pub email: String,
 }
 
+purpose_claims!(VerifyEmailClaims, VerifyEmail);
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResetPasswordClaims {
+    pub exp: usize,
+    pub purpose: TokenPurpose,
+    pub email: String,
+    /// The hash of the password hash when the code is created,
+    /// the code is invalid after the password is changed.
+    pub password: String,
+}
+
+purpose_claims!(ResetPasswordClaims, ResetPassword);
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UnlockAccountClaims {
+    pub exp: usize,
+    pub purpose: TokenPurpose,
+    pub unlock_email: String,
+}
+
+purpose_claims!(UnlockAccountClaims, UnlockAccount);
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MagicLinkClaims {
+    pub exp: usize,
+    pub purpose: TokenPurpose,
+    pub email: String,
+    /// The random nonce, the link is invalid after it is used or a new link is sent
+    pub nonce: String,
+}
+
+purpose_claims!(MagicLinkClaims, MagicLink);
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CancelDeletionClaims {
+    pub exp: usize,
+    pub purpose: TokenPurpose,
+    /// The user id
+    pub id: String,
+    /// The purge time (as UTC timestamp in milliseconds) of the deletion request
+    pub delete_at: i64,
+}
+
+purpose_claims!(CancelDeletionClaims, CancelDeletion);
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChangeEmailClaims {
+    pub exp: usize,
+    pub purpose: TokenPurpose,
+    /// The user id
+    pub id: String,
+    /// The current email, the code is invalid after the email is changed
+    pub email: String,
+    pub new_email: String,
+}
+
+purpose_claims!(ChangeEmailClaims, ChangeEmail);
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StaffEmailClaims {
+    pub exp: usize,
+    pub purpose: TokenPurpose,
+    /// The id of the teacher verification request
+    pub verification_id: String,
+    pub staff_email: String,
+}
+
+purpose_claims!(StaffEmailClaims, StaffEmail);
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConnectConfirmClaims {
+    pub exp: usize,
+    pub purpose: TokenPurpose,
+    /// The user id
+    pub id: String,
+    /// The email of the user, the code is only sent to this email
+    pub confirm_email: String,
+    /// The account waiting to be linked
+    pub connect: ConnectAccount,
+}
+
+purpose_claims!(ConnectConfirmClaims, ConnectConfirm);
+
 pub fn send_verify_email(
     username: &str,
     password: &str,
@@ -30,10 +118,277 @@ pub fn send_verify_email(
         }
     };
 
+    send_email(
+        username,
+        password,
+        to_email,
+        "Lipoic Email Verify",
+        email_html,
+    );
+}
+
+pub fn send_reset_password_email(
+    username: &str,
+    password: &str,
+    reset_password_url: &str,
+    code: String,
+    to_email: &str,
+) {
+    let url = format!("{}?code={}", reset_password_url, code);
+    let email_html = html! {
+        head {
+            title { "Lipoic Reset Password" }
+        }
+        div {
+            p { "If you did not request a password reset, please ignore this email." }
+            a href=(url) { "Reset Password" }
+        }
+    };
+
+    send_email(
+        username,
+        password,
+        to_email,
+        "Lipoic Reset Password",
+        email_html,
+    );
+}
+
+pub fn send_connect_confirm_email(
+    username: &str,
+    password: &str,
+    connect_confirm_url: &str,
+    code: String,
+    connect_email: &str,
+    to_email: &str,
+) {
+    let url = format!("{}?code={}", connect_confirm_url, code);
+    let email_html = html! {
+        head {
+            title { "Lipoic Link Account" }
+        }
+        div {
+            p { "The account " (connect_email) " is requested to be linked to your Lipoic account." }
+            p { "If you did not request it, please ignore this email." }
+            a href=(url) { "Link Account" }
+        }
+    };
+
+    send_email(
+        username,
+        password,
+        to_email,
+        "Lipoic Link Account",
+        email_html,
+    );
+}
+
+pub fn send_password_changed_email(username: &str, password: &str, to_email: &str) {
+    let email_html = html! {
+        head {
+            title { "Lipoic Password Changed" }
+        }
+        div {
+            p { "The password of your Lipoic account has been changed." }
+            p { "If you did not change the password, please reset your password immediately." }
+        }
+    };
+
+    send_email(
+        username,
+        password,
+        to_email,
+        "Lipoic Password Changed",
+        email_html,
+    );
+}
+
+pub fn send_unlock_account_email(
+    username: &str,
+    password: &str,
+    unlock_account_url: &str,
+    code: String,
+    to_email: &str,
+) {
+    let url = format!("{}?code={}", unlock_account_url, code);
+    let email_html = html! {
+        head {
+            title { "Lipoic Account Locked" }
+        }
+        div {
+            p { "Your Lipoic account is temporarily locked because of too many failed logins." }
+            p { "If it was you, unlock the account by the link, otherwise please change your password." }
+            a href=(url) { "Unlock Account" }
+        }
+    };
+
+    send_email(
+        username,
+        password,
+        to_email,
+        "Lipoic Account Locked",
+        email_html,
+    );
+}
+
+pub fn send_magic_link_email(
+    username: &str,
+    password: &str,
+    magic_link_url: &str,
+    code: String,
+    to_email: &str,
+) {
+    let url = format!("{}?code={}", magic_link_url, code);
+    let email_html = html! {
+        head {
+            title { "Lipoic Login" }
+        }
+        div {
+            p { "Click the link to login to Lipoic, the link expires in 10 minutes." }
+            p { "If you did not request it, please ignore this email." }
+            a href=(url) { "Login" }
+        }
+    };
+
+    send_email(username, password, to_email, "Lipoic Login", email_html);
+}
+
+pub fn send_account_deletion_email(
+    username: &str,
+    password: &str,
+    cancel_deletion_url: &str,
+    code: String,
+    grace_days: i64,
+    to_email: &str,
+) {
+    let url = format!("{}?code={}", cancel_deletion_url, code);
+    let email_html = html! {
+        head {
+            title { "Lipoic Account Deletion" }
+        }
+        div {
+            p { "Your Lipoic account will be deleted in " (grace_days) " days." }
+            p { "If you did not request it or changed your mind, please cancel the deletion." }
+            a href=(url) { "Cancel Deletion" }
+        }
+    };
+
+    send_email(
+        username,
+        password,
+        to_email,
+        "Lipoic Account Deletion",
+        email_html,
+    );
+}
+
+pub fn send_data_export_email(username: &str, password: &str, download_url: &str, to_email: &str) {
+    let email_html = html! {
+        head {
+            title { "Lipoic Data Export" }
+        }
+        div {
+            p { "The export of your Lipoic data is ready, the link expires in 24 hours." }
+            p { "If you did not request it, please change your password immediately." }
+            a href=(download_url) { "Download" }
+        }
+    };
+
+    send_email(
+        username,
+        password,
+        to_email,
+        "Lipoic Data Export",
+        email_html,
+    );
+}
+
+pub fn send_change_email_confirm_email(
+    username: &str,
+    password: &str,
+    change_email_url: &str,
+    code: String,
+    to_email: &str,
+) {
+    let url = format!("{}?code={}", change_email_url, code);
+    let email_html = html! {
+        head {
+            title { "Lipoic Change Email" }
+        }
+        div {
+            p { "Please confirm this email to use it for your Lipoic account." }
+            p { "If you did not request it, please ignore this email." }
+            a href=(url) { "Confirm Email" }
+        }
+    };
+
+    send_email(
+        username,
+        password,
+        to_email,
+        "Lipoic Change Email",
+        email_html,
+    );
+}
+
+pub fn send_staff_email_confirm_email(
+    username: &str,
+    password: &str,
+    staff_email_confirm_url: &str,
+    code: String,
+    to_email: &str,
+) {
+    let url = format!("{}?code={}", staff_email_confirm_url, code);
+    let email_html = html! {
+        head {
+            title { "Lipoic Teacher Verification" }
+        }
+        div {
+            p { "Please confirm this staff email for the teacher verification of your Lipoic account." }
+            p { "If you did not request it, please ignore this email." }
+            a href=(url) { "Confirm Staff Email" }
+        }
+    };
+
+    send_email(
+        username,
+        password,
+        to_email,
+        "Lipoic Teacher Verification",
+        email_html,
+    );
+}
+
+pub fn send_email_change_requested_email(
+    username: &str,
+    password: &str,
+    new_email: &str,
+    to_email: &str,
+) {
+    let email_html = html! {
+        head {
+            title { "Lipoic Email Change Requested" }
+        }
+        div {
+            p { "The email of your Lipoic account is requested to be changed to " (new_email) "." }
+            p { "If you did not request it, please change your password immediately." }
+        }
+    };
+
+    send_email(
+        username,
+        password,
+        to_email,
+        "Lipoic Email Change Requested",
+        email_html,
+    );
+}
+
+fn send_email(username: &str, password: &str, to_email: &str, subject: &str, email_html: Markup) {
     let email = Message::builder()
         .from(username.parse().unwrap())
         .to(to_email.parse().unwrap())
-        .subject("Lipoic Email Verify")
+        .subject(subject)
         .multipart(
             MultiPart::alternative() // This is composed of two parts.
                 .singlepart(
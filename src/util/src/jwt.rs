@@ -1,13 +1,61 @@
 pub use jsonwebtoken::errors;
-use jsonwebtoken::errors::Result;
+use jsonwebtoken::errors::{ErrorKind, Result};
 use jsonwebtoken::{
     decode, encode, Algorithm, DecodingKey, EncodingKey, Header, TokenData, Validation,
 };
 use serde::de::DeserializeOwned;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+
+/// What the token is for, a token is only accepted for its own purpose
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum TokenPurpose {
+    Access,
+    Mfa,
+    Link,
+    OAuthState,
+    Passkey,
+    VerifyEmail,
+    ResetPassword,
+    UnlockAccount,
+    MagicLink,
+    CancelDeletion,
+    ChangeEmail,
+    StaffEmail,
+    ConnectConfirm,
+}
+
+/// The claims of a JWT token with a `purpose` field.
+///
+/// All the tokens are signed by the same key, so the purpose is checked when the token is verified.
+pub trait PurposeClaims {
+    const PURPOSE: TokenPurpose;
+
+    fn purpose(&self) -> TokenPurpose;
+}
+
+/// Implement [PurposeClaims] for the claims with a `purpose` field
+/// # Example
+/// ```rust,ignore
+/// purpose_claims!(VerifyEmailClaims, VerifyEmail);
+/// ```
+#[macro_export]
+macro_rules! purpose_claims {
+    ($claims:ty, $purpose:ident) => {
+        impl $crate::jwt::PurposeClaims for $claims {
+            const PURPOSE: $crate::jwt::TokenPurpose = $crate::jwt::TokenPurpose::$purpose;
+
+            fn purpose(&self) -> $crate::jwt::TokenPurpose {
+                self.purpose
+            }
+        }
+    };
+}
 
 /// create a new JWT token
-pub fn create_jwt_token<T: Serialize>(private_key: &[u8], claims: T) -> Result<String> {
+pub fn create_jwt_token<T: Serialize + PurposeClaims>(
+    private_key: &[u8],
+    claims: T,
+) -> Result<String> {
     encode(
         &Header::new(Algorithm::RS256),
         &claims,
@@ -15,11 +63,20 @@ pub fn create_jwt_token<T: Serialize>(private_key: &[u8], claims: T) -> Result<S
     )
 }
 
-/// verify JWT token correctness
-pub fn verify_token<T: DeserializeOwned>(token: String, public_key: &[u8]) -> Result<TokenData<T>> {
-    decode::<T>(
+/// verify JWT token correctness and the purpose of the token
+pub fn verify_token<T: DeserializeOwned + PurposeClaims>(
+    token: String,
+    public_key: &[u8],
+) -> Result<TokenData<T>> {
+    let token_data = decode::<T>(
         token.as_str(),
         &DecodingKey::from_rsa_pem(public_key)?,
         &Validation::new(Algorithm::RS256),
-    )
+    )?;
+
+    if token_data.claims.purpose() != T::PURPOSE {
+        return Err(ErrorKind::InvalidToken.into());
+    }
+
+    Ok(token_data)
 }
@@ -2,4 +2,10 @@ pub mod bcrypt;
 pub mod email;
 pub mod jwt;
 pub mod oauth;
+pub mod password;
+pub mod rate_limit;
+pub mod role;
+pub mod totp;
+pub mod user_agent;
 pub mod util;
+pub mod webauthn;
@@ -0,0 +1,107 @@
+use async_trait::async_trait;
+use database::model::auth::user::ConnectType;
+use serde::Deserialize;
+
+use super::{AccessTokenInfo, OAuthAccountInfo, OAuthError, OAuthProvider};
+
+const FACEBOOK_AUTH_URL: &str = "https://www.facebook.com/dialog/oauth";
+const FACEBOOK_TOKEN_URL: &str = "https://graph.facebook.com/v14.0/oauth/access_token";
+const FACEBOOK_USER_INFO: &str = "https://graph.facebook.com/v14.0";
+
+pub struct Facebook;
+
+#[derive(Deserialize)]
+pub struct FacebookAccountInfo {
+    pub id: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub name: String,
+    pub email: String,
+    pub picture: FacebookAccountPicture,
+}
+
+#[derive(Deserialize)]
+pub struct FacebookAccountPicture {
+    pub data: FacebookAccountPictureData,
+}
+
+#[derive(Deserialize)]
+pub struct FacebookAccountPictureData {
+    pub height: i32,
+    pub is_silhouette: bool,
+    pub url: String,
+    pub width: i32,
+}
+
+#[async_trait]
+impl OAuthProvider for Facebook {
+    fn name(&self) -> &str {
+        "facebook"
+    }
+
+    fn account_type(&self) -> ConnectType {
+        ConnectType::Facebook
+    }
+
+    fn auth_url(&self) -> &str {
+        FACEBOOK_AUTH_URL
+    }
+
+    fn token_url(&self) -> &str {
+        FACEBOOK_TOKEN_URL
+    }
+
+    fn scope(&self) -> &str {
+        "public_profile,email"
+    }
+
+    async fn request_token(
+        &self,
+        form_data: Vec<(&'static str, String)>,
+    ) -> Result<AccessTokenInfo, reqwest::Error> {
+        let form_data: Vec<(&str, String)> = form_data
+            .into_iter()
+            .map(|(key, value)| match key {
+                // Because Facebook requires "/" at the end of the redirect uri
+                "redirect_uri" if !value.ends_with('/') => (key, format!("{}/", value)),
+                _ => (key, value),
+            })
+            .collect();
+
+        let response = reqwest::Client::new()
+            .get(FACEBOOK_TOKEN_URL)
+            .query(&form_data)
+            .send()
+            .await?;
+
+        response.json::<AccessTokenInfo>().await
+    }
+
+    async fn get_account_info(
+        &self,
+        access_token_info: &AccessTokenInfo,
+    ) -> Result<OAuthAccountInfo, OAuthError> {
+        let response = reqwest::Client::new()
+            .get(format!(
+                "{}/me?fields=id,first_name,last_name,name,email,picture&access_token={}",
+                FACEBOOK_USER_INFO,
+                access_token_info.access_token.clone()
+            ))
+            .send()
+            .await?;
+
+        let facebook_account_info = response.json::<FacebookAccountInfo>().await?;
+
+        Ok(OAuthAccountInfo {
+            id: facebook_account_info.id,
+            name: facebook_account_info.name,
+            email: facebook_account_info.email,
+            picture: facebook_account_info.picture.data.url,
+            // Facebook doesn't tell whether the email is verified,
+            // so linking the account to the user of the same email must be confirmed.
+            verified_email: false,
+            school_id: None,
+            grade: None,
+        })
+    }
+}
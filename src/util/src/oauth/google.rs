@@ -0,0 +1,69 @@
+use async_trait::async_trait;
+use database::model::auth::user::ConnectType;
+use serde::Deserialize;
+
+use super::{AccessTokenInfo, OAuthAccountInfo, OAuthError, OAuthProvider};
+
+const GOOGLE_AUTH_URL: &str = "https://accounts.google.com/o/oauth2/auth";
+const GOOGLE_TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+const GOOGLE_USER_INFO: &str = "https://www.googleapis.com/oauth2/v1/userinfo?alt=json";
+
+pub struct Google;
+
+#[derive(Deserialize)]
+pub struct GoogleAccountInfo {
+    pub id: String,
+    pub email: String,
+    pub verified_email: bool,
+    pub name: String,
+    pub given_name: String,
+    pub family_name: String,
+    pub picture: String,
+    pub locale: String,
+}
+
+#[async_trait]
+impl OAuthProvider for Google {
+    fn name(&self) -> &str {
+        "google"
+    }
+
+    fn account_type(&self) -> ConnectType {
+        ConnectType::Google
+    }
+
+    fn auth_url(&self) -> &str {
+        GOOGLE_AUTH_URL
+    }
+
+    fn token_url(&self) -> &str {
+        GOOGLE_TOKEN_URL
+    }
+
+    fn scope(&self) -> &str {
+        "https://www.googleapis.com/auth/userinfo.profile https://www.googleapis.com/auth/userinfo.email"
+    }
+
+    async fn get_account_info(
+        &self,
+        access_token_info: &AccessTokenInfo,
+    ) -> Result<OAuthAccountInfo, OAuthError> {
+        let response = reqwest::Client::new()
+            .get(GOOGLE_USER_INFO)
+            .bearer_auth(access_token_info.access_token.clone())
+            .send()
+            .await?;
+
+        let google_account_info = response.json::<GoogleAccountInfo>().await?;
+
+        Ok(OAuthAccountInfo {
+            id: google_account_info.id,
+            name: google_account_info.name,
+            email: google_account_info.email,
+            picture: google_account_info.picture,
+            verified_email: google_account_info.verified_email,
+            school_id: None,
+            grade: None,
+        })
+    }
+}
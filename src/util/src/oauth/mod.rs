@@ -0,0 +1,159 @@
+mod facebook;
+mod google;
+mod openid_connect;
+mod taiwan_cloud_education;
+
+use async_trait::async_trait;
+use database::model::auth::user::ConnectType;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use urlencoding::encode;
+
+pub use facebook::{Facebook, FacebookAccountInfo};
+pub use google::{Google, GoogleAccountInfo};
+pub use openid_connect::{OpenIdClaims, OpenIdConfiguration, OpenIdConnect, OpenIdConnectError};
+pub use taiwan_cloud_education::{
+    TaiwanCloudEducation, TaiwanCloudEducationAccountInfo, TaiwanCloudEducationClassInfo,
+    TaiwanCloudEducationEduInfo,
+};
+
+pub type OAuthError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A OAuth2 provider, implement it and add it to [`providers`] to support a new provider
+///
+/// The OpenID Connect providers are created by [`OpenIdConnect::discover`] from the config instead.
+#[async_trait]
+pub trait OAuthProvider: Send + Sync {
+    /// The name of the provider, used in the routes (`/authentication/<name>`) and the config
+    fn name(&self) -> &str;
+
+    fn account_type(&self) -> ConnectType;
+
+    fn auth_url(&self) -> &str;
+
+    fn token_url(&self) -> &str;
+
+    /// The scope of the auth url
+    fn scope(&self) -> &str;
+
+    /// request the access token with the form data of the code
+    ///
+    /// The default is the standard OAuth2 token request (`POST` form with `grant_type`)
+    async fn request_token(
+        &self,
+        mut form_data: Vec<(&'static str, String)>,
+    ) -> Result<AccessTokenInfo, reqwest::Error> {
+        form_data.push(("grant_type", "authorization_code".to_string()));
+
+        let response = reqwest::Client::new()
+            .post(self.token_url())
+            .form(&form_data)
+            .send()
+            .await?;
+
+        response.json::<AccessTokenInfo>().await
+    }
+
+    /// request the account info of the access token
+    async fn get_account_info(
+        &self,
+        access_token_info: &AccessTokenInfo,
+    ) -> Result<OAuthAccountInfo, OAuthError>;
+}
+
+/// The built-in OAuth providers
+pub fn providers() -> Vec<Box<dyn OAuthProvider>> {
+    vec![
+        Box::new(Google),
+        Box::new(Facebook),
+        Box::new(TaiwanCloudEducation),
+    ]
+}
+
+pub struct OAuthData<'a> {
+    pub provider: &'a dyn OAuthProvider,
+    pub client_secret: String,
+    pub client_id: String,
+    pub redirect_uri: String,
+}
+
+#[derive(Deserialize)]
+pub struct AccessTokenInfo {
+    pub access_token: String,
+    pub expires_in: i32,
+    /// Appears only in google OAuth
+    #[serde(skip_deserializing)]
+    pub scope: String,
+    pub token_type: String,
+    /// Appears only in OpenID Connect
+    #[serde(default)]
+    pub id_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct OAuthAccountInfo {
+    pub id: String,
+    pub name: String,
+    pub email: String,
+    pub picture: String,
+    pub verified_email: bool,
+    /// The school id, appears only in Taiwan Cloud Education
+    pub school_id: Option<String>,
+    /// The grade, appears only in Taiwan Cloud Education
+    pub grade: Option<String>,
+}
+
+impl OAuthData<'_> {
+    /// get oauth url with the `state` and the PKCE `code_challenge` (`S256`)
+    ///
+    /// return one url [`String`]
+    pub fn get_auth_url(&self, state: &str, code_challenge: &str) -> String {
+        format!(
+            "{}?client_id={}&response_type=code&scope={}&redirect_uri={}&state={}&code_challenge={}&code_challenge_method=S256",
+            self.provider.auth_url(),
+            self.client_id,
+            encode(self.provider.scope()),
+            encode(&self.redirect_uri),
+            encode(state),
+            encode(code_challenge)
+        )
+    }
+
+    /// get access token info by code and the PKCE `code_verifier`
+    ///
+    /// return [`AccessTokenInfo`]
+    pub async fn authorization_code(
+        &self,
+        code: String,
+        code_verifier: &str,
+    ) -> Result<AccessTokenInfo, reqwest::Error> {
+        self.provider
+            .request_token(vec![
+                ("client_id", self.client_id.clone()),
+                ("client_secret", self.client_secret.clone()),
+                ("code", code),
+                ("redirect_uri", self.redirect_uri.clone()),
+                ("code_verifier", code_verifier.to_string()),
+            ])
+            .await
+    }
+}
+
+/// create the PKCE `code_verifier` of the `state`
+///
+/// The verifier is derived from the state with the server secret, so it doesn't need to be stored.
+pub fn create_code_verifier(secret: &[u8], state: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC can take key of any size");
+    mac.update(state.as_bytes());
+
+    base64::encode_config(mac.finalize().into_bytes(), base64::URL_SAFE_NO_PAD)
+}
+
+/// create the PKCE `code_challenge` of the `code_verifier` (`S256`)
+pub fn create_code_challenge(code_verifier: &str) -> String {
+    base64::encode_config(
+        Sha256::digest(code_verifier.as_bytes()),
+        base64::URL_SAFE_NO_PAD,
+    )
+}
@@ -0,0 +1,212 @@
+use std::sync::RwLock;
+
+use async_trait::async_trait;
+use database::model::auth::user::ConnectType;
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, DecodingKey, Validation};
+use serde::Deserialize;
+
+use super::{AccessTokenInfo, OAuthAccountInfo, OAuthError, OAuthProvider};
+
+/// The OpenID Connect discovery document (`.well-known/openid-configuration`)
+#[derive(Deserialize)]
+pub struct OpenIdConfiguration {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub userinfo_endpoint: Option<String>,
+    pub jwks_uri: String,
+}
+
+/// The claims of the `id_token` or the userinfo response
+#[derive(Deserialize)]
+pub struct OpenIdClaims {
+    pub sub: String,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    pub name: Option<String>,
+    pub preferred_username: Option<String>,
+    pub picture: Option<String>,
+}
+
+/// A generic OpenID Connect provider, e.g. Keycloak or Azure AD
+pub struct OpenIdConnect {
+    name: String,
+    client_id: String,
+    configuration: OpenIdConfiguration,
+    /// The signing keys of the provider, refreshed when a unknown key id is found
+    jwks: RwLock<JwkSet>,
+}
+
+#[derive(Debug)]
+pub enum OpenIdConnectError {
+    /// The issuer of the discovery document is different from the configured issuer
+    IssuerMismatch,
+    /// The token response doesn't have the `id_token`
+    MissingIdToken,
+    /// The signing key of the `id_token` is not found in the JWKS
+    KeyNotFound,
+    /// The account doesn't have a email
+    MissingEmail,
+}
+
+impl std::fmt::Display for OpenIdConnectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for OpenIdConnectError {}
+
+impl OpenIdConnect {
+    pub fn new(
+        name: &str,
+        client_id: &str,
+        configuration: OpenIdConfiguration,
+        jwks: JwkSet,
+    ) -> Self {
+        OpenIdConnect {
+            name: name.to_string(),
+            client_id: client_id.to_string(),
+            configuration,
+            jwks: RwLock::new(jwks),
+        }
+    }
+
+    /// create the provider from the discovery document of the `issuer`
+    pub async fn discover(name: &str, issuer: &str, client_id: &str) -> Result<Self, OAuthError> {
+        let configuration = reqwest::get(format!(
+            "{}/.well-known/openid-configuration",
+            issuer.trim_end_matches('/')
+        ))
+        .await?
+        .json::<OpenIdConfiguration>()
+        .await?;
+
+        if configuration.issuer.trim_end_matches('/') != issuer.trim_end_matches('/') {
+            return Err(OpenIdConnectError::IssuerMismatch.into());
+        }
+
+        let jwks = fetch_jwks(&configuration.jwks_uri).await?;
+
+        Ok(OpenIdConnect::new(name, client_id, configuration, jwks))
+    }
+
+    fn decoding_key(&self, kid: &Option<String>) -> Option<DecodingKey> {
+        let jwks = self.jwks.read().unwrap();
+
+        let jwk = match kid {
+            Some(kid) => jwks.find(kid),
+            None => jwks.keys.first(),
+        }?;
+
+        DecodingKey::from_jwk(jwk).ok()
+    }
+
+    /// verify the signature, issuer, audience and expiration time of the `id_token`
+    ///
+    /// return [`OpenIdClaims`]
+    pub async fn verify_id_token(&self, id_token: &str) -> Result<OpenIdClaims, OAuthError> {
+        let header = decode_header(id_token)?;
+
+        let decoding_key = match self.decoding_key(&header.kid) {
+            Some(decoding_key) => decoding_key,
+            None => {
+                // The provider may have rotated the keys
+                let jwks = fetch_jwks(&self.configuration.jwks_uri).await?;
+                *self.jwks.write().unwrap() = jwks;
+
+                self.decoding_key(&header.kid)
+                    .ok_or(OpenIdConnectError::KeyNotFound)?
+            }
+        };
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&self.configuration.issuer]);
+        validation.set_audience(&[&self.client_id]);
+
+        Ok(decode::<OpenIdClaims>(id_token, &decoding_key, &validation)?.claims)
+    }
+
+    /// request the userinfo, for the providers which don't put the email into the `id_token`
+    async fn get_user_info(
+        &self,
+        userinfo_endpoint: &str,
+        access_token_info: &AccessTokenInfo,
+    ) -> Result<OpenIdClaims, OAuthError> {
+        let response = reqwest::Client::new()
+            .get(userinfo_endpoint)
+            .bearer_auth(access_token_info.access_token.clone())
+            .send()
+            .await?;
+
+        Ok(response.json::<OpenIdClaims>().await?)
+    }
+}
+
+async fn fetch_jwks(jwks_uri: &str) -> Result<JwkSet, reqwest::Error> {
+    reqwest::get(jwks_uri).await?.json::<JwkSet>().await
+}
+
+#[async_trait]
+impl OAuthProvider for OpenIdConnect {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn account_type(&self) -> ConnectType {
+        ConnectType::OpenIdConnect(self.configuration.issuer.clone())
+    }
+
+    fn auth_url(&self) -> &str {
+        &self.configuration.authorization_endpoint
+    }
+
+    fn token_url(&self) -> &str {
+        &self.configuration.token_endpoint
+    }
+
+    fn scope(&self) -> &str {
+        "openid email profile"
+    }
+
+    async fn get_account_info(
+        &self,
+        access_token_info: &AccessTokenInfo,
+    ) -> Result<OAuthAccountInfo, OAuthError> {
+        let id_token = access_token_info
+            .id_token
+            .as_ref()
+            .ok_or(OpenIdConnectError::MissingIdToken)?;
+
+        let mut claims = self.verify_id_token(id_token).await?;
+
+        if claims.email.is_none() {
+            if let Some(userinfo_endpoint) = &self.configuration.userinfo_endpoint {
+                let user_info = self
+                    .get_user_info(userinfo_endpoint, access_token_info)
+                    .await?;
+
+                // The userinfo must be the same user of the id token
+                if user_info.sub == claims.sub {
+                    claims = user_info;
+                }
+            }
+        }
+
+        let email = claims.email.ok_or(OpenIdConnectError::MissingEmail)?;
+
+        Ok(OAuthAccountInfo {
+            id: claims.sub,
+            name: claims
+                .name
+                .or(claims.preferred_username)
+                .unwrap_or_else(|| email.clone()),
+            email,
+            picture: claims.picture.unwrap_or_default(),
+            verified_email: claims.email_verified.unwrap_or(false),
+            school_id: None,
+            grade: None,
+        })
+    }
+}
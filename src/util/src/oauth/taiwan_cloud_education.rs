@@ -0,0 +1,120 @@
+use async_trait::async_trait;
+use database::model::auth::user::ConnectType;
+use serde::Deserialize;
+
+use super::{AccessTokenInfo, OAuthAccountInfo, OAuthError, OAuthProvider};
+
+const TCE_AUTH_URL: &str = "https://oidc.tanet.edu.tw/oidc/v1/azp";
+const TCE_TOKEN_URL: &str = "https://oidc.tanet.edu.tw/oidc/v1/token";
+const TCE_USER_INFO: &str = "https://oidc.tanet.edu.tw/oidc/v1/userinfo";
+const TCE_EDU_INFO: &str = "https://oidc.tanet.edu.tw/moeresource/api/v1/oidc/eduinfo";
+
+/// Taiwan Cloud Education (教育雲)
+pub struct TaiwanCloudEducation;
+
+#[derive(Deserialize)]
+pub struct TaiwanCloudEducationAccountInfo {
+    pub sub: String,
+    pub name: String,
+    pub email: String,
+    pub email_verified: Option<bool>,
+}
+
+/// The school info of the Taiwan Cloud Education account (`eduinfo` scope)
+#[derive(Deserialize)]
+pub struct TaiwanCloudEducationEduInfo {
+    pub sub: String,
+    #[serde(rename = "schoolid")]
+    pub school_id: Option<String>,
+    #[serde(rename = "classinfo", default)]
+    pub class_info: Vec<TaiwanCloudEducationClassInfo>,
+}
+
+#[derive(Deserialize)]
+pub struct TaiwanCloudEducationClassInfo {
+    #[serde(rename = "schoolid")]
+    pub school_id: Option<String>,
+    pub grade: Option<String>,
+    #[serde(rename = "classno")]
+    pub class_no: Option<String>,
+}
+
+impl TaiwanCloudEducation {
+    /// request Taiwan Cloud Education school info
+    ///
+    /// return [`TaiwanCloudEducationEduInfo`]
+    async fn get_edu_info(
+        &self,
+        access_token_info: &AccessTokenInfo,
+    ) -> Result<TaiwanCloudEducationEduInfo, OAuthError> {
+        let response = reqwest::Client::new()
+            .get(TCE_EDU_INFO)
+            .bearer_auth(access_token_info.access_token.clone())
+            .send()
+            .await?;
+
+        Ok(response.json::<TaiwanCloudEducationEduInfo>().await?)
+    }
+}
+
+#[async_trait]
+impl OAuthProvider for TaiwanCloudEducation {
+    fn name(&self) -> &str {
+        "tce"
+    }
+
+    fn account_type(&self) -> ConnectType {
+        ConnectType::TaiwanCloudEducation
+    }
+
+    fn auth_url(&self) -> &str {
+        TCE_AUTH_URL
+    }
+
+    fn token_url(&self) -> &str {
+        TCE_TOKEN_URL
+    }
+
+    fn scope(&self) -> &str {
+        "openid email profile eduinfo"
+    }
+
+    async fn get_account_info(
+        &self,
+        access_token_info: &AccessTokenInfo,
+    ) -> Result<OAuthAccountInfo, OAuthError> {
+        let response = reqwest::Client::new()
+            .get(TCE_USER_INFO)
+            .bearer_auth(access_token_info.access_token.clone())
+            .send()
+            .await?;
+
+        let account_info = response.json::<TaiwanCloudEducationAccountInfo>().await?;
+
+        // Not every account has the school info, e.g. the teachers of some schools
+        let (school_id, grade) = match self.get_edu_info(access_token_info).await {
+            Ok(edu_info) if edu_info.sub == account_info.sub => {
+                let class_info = edu_info.class_info.into_iter().next();
+
+                (
+                    edu_info
+                        .school_id
+                        .or_else(|| class_info.as_ref().and_then(|info| info.school_id.clone())),
+                    class_info.and_then(|info| info.grade),
+                )
+            }
+            _ => (None, None),
+        };
+
+        Ok(OAuthAccountInfo {
+            id: account_info.sub,
+            name: account_info.name,
+            email: account_info.email,
+            // Taiwan Cloud Education doesn't provide the picture
+            picture: String::new(),
+            verified_email: account_info.email_verified.unwrap_or(false),
+            school_id,
+            grade,
+        })
+    }
+}
@@ -0,0 +1,9 @@
+/// The minimum length of a password
+pub const PASSWORD_MIN_LENGTH: usize = 8;
+
+/// check the password has at least [`PASSWORD_MIN_LENGTH`] characters, including letters and numbers
+pub fn is_strong_password(password: &str) -> bool {
+    password.chars().count() >= PASSWORD_MIN_LENGTH
+        && password.chars().any(|c| c.is_alphabetic())
+        && password.chars().any(|c| c.is_numeric())
+}
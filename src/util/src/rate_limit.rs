@@ -0,0 +1,51 @@
+use std::time::Instant;
+
+/// A token bucket, refilled with `rate` tokens per second up to the `capacity`
+pub struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    /// create a full bucket
+    pub fn new(capacity: u32, now: Instant) -> Self {
+        TokenBucket {
+            tokens: capacity as f64,
+            updated: now,
+        }
+    }
+
+    /// the last time a token is taken
+    pub fn updated(&self) -> Instant {
+        self.updated
+    }
+
+    /// the tokens of the bucket at `now`
+    pub fn tokens_at(&self, capacity: u32, rate: f64, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        (self.tokens + elapsed * rate).min(capacity as f64)
+    }
+
+    /// take a token from the bucket at `now`
+    ///
+    /// return the seconds to wait if the bucket is empty
+    pub fn take(&mut self, capacity: u32, rate: f64, now: Instant) -> Result<(), u64> {
+        self.tokens = self.tokens_at(capacity, rate, now);
+        self.updated = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(((1.0 - self.tokens) / rate).ceil() as u64)
+        }
+    }
+}
+
+/// check the path matches the pattern, a pattern ending with `*` matches the prefix
+pub fn path_matches(pattern: &str, path: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => path.starts_with(prefix),
+        None => path == pattern,
+    }
+}
@@ -0,0 +1,36 @@
+use database::model::auth::user::Role;
+use serde::Serialize;
+
+/// An action which is only allowed for some roles
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    /// Manage the users and grant the roles
+    ManageUsers,
+    /// Review the teacher verification requests
+    ReviewTeachers,
+    /// Create and manage the courses
+    ManageCourses,
+}
+
+/// The permissions of the role
+pub fn role_permissions(role: Role) -> &'static [Permission] {
+    match role {
+        Role::Admin => &[
+            Permission::ManageUsers,
+            Permission::ReviewTeachers,
+            Permission::ManageCourses,
+        ],
+        Role::SchoolAdmin => &[Permission::ReviewTeachers, Permission::ManageCourses],
+        Role::Teacher => &[Permission::ManageCourses],
+    }
+}
+
+/// Check the roles have all the permissions of the `required` role
+pub fn roles_include(roles: &[Role], required: Role) -> bool {
+    roles.iter().any(|&role| {
+        role == required
+            || role_permissions(required)
+                .iter()
+                .all(|permission| role_permissions(role).contains(permission))
+    })
+}
@@ -0,0 +1,79 @@
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha1::Sha1;
+use std::time::{SystemTime, UNIX_EPOCH};
+use urlencoding::encode;
+
+/// The time step of the code in seconds
+const TOTP_STEP: u64 = 30;
+/// The number of digits of the code
+const TOTP_DIGITS: u32 = 6;
+/// The number of steps before and after the current step that are accepted
+const TOTP_SKEW: i64 = 1;
+
+/// create a random base32 secret (160 bits)
+pub fn create_totp_secret() -> String {
+    let mut secret = [0u8; 20];
+    rand::thread_rng().fill_bytes(&mut secret);
+
+    BASE32_NOPAD.encode(&secret)
+}
+
+/// create the `otpauth://` uri of the secret, used as the payload of the QR code
+pub fn create_totp_uri(secret: &str, account: &str, issuer: &str) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        encode(issuer),
+        encode(account),
+        secret,
+        encode(issuer),
+        TOTP_DIGITS,
+        TOTP_STEP
+    )
+}
+
+/// create the code of the secret at `time` (as UTC timestamp)
+///
+/// return [`None`] if the secret is not valid base32
+pub fn create_totp_code(secret: &str, time: u64) -> Option<String> {
+    let key = BASE32_NOPAD.decode(secret.as_bytes()).ok()?;
+    let mut mac = Hmac::<Sha1>::new_from_slice(&key).ok()?;
+    mac.update(&(time / TOTP_STEP).to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    // Dynamic truncation (RFC 4226)
+    let offset = (hash[hash.len() - 1] & 0xf) as usize;
+    let binary = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+
+    Some(format!(
+        "{:0width$}",
+        binary % 10u32.pow(TOTP_DIGITS),
+        width = TOTP_DIGITS as usize
+    ))
+}
+
+/// verify the code of the secret, the codes of the adjacent time steps are accepted
+pub fn verify_totp_code(secret: &str, code: &str) -> bool {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs();
+
+    verify_totp_step(secret, code, now).is_some()
+}
+
+/// verify the code of the secret at `time` (as UTC timestamp)
+///
+/// return the time step of the matched code, so the caller can reject a code that is used again
+pub fn verify_totp_step(secret: &str, code: &str, time: u64) -> Option<u64> {
+    (-TOTP_SKEW..=TOTP_SKEW)
+        .map(|skew| (time as i64 + skew * TOTP_STEP as i64) as u64)
+        .find(|time| create_totp_code(secret, *time).as_deref() == Some(code))
+        .map(|time| time / TOTP_STEP)
+}
@@ -0,0 +1,36 @@
+/// The browsers, the first matched token is used, e.g. Edge has the `Chrome` token too
+const BROWSERS: [(&str, &str); 6] = [
+    ("Edg/", "Edge"),
+    ("OPR/", "Opera"),
+    ("Firefox/", "Firefox"),
+    ("CriOS/", "Chrome"),
+    ("Chrome/", "Chrome"),
+    ("Safari/", "Safari"),
+];
+
+/// The operating systems, the first matched token is used, e.g. Android has the `Linux` token too
+const OPERATING_SYSTEMS: [(&str, &str); 7] = [
+    ("CrOS", "ChromeOS"),
+    ("Android", "Android"),
+    ("iPhone", "iOS"),
+    ("iPad", "iPadOS"),
+    ("Windows", "Windows"),
+    ("Mac OS X", "macOS"),
+    ("Linux", "Linux"),
+];
+
+/// get a readable device name from the `User-Agent` header, e.g. `Chrome on ChromeOS`
+pub fn device_name(user_agent: &str) -> String {
+    let find = |names: &[(&str, &'static str)]| {
+        names
+            .iter()
+            .find(|(token, _)| user_agent.contains(token))
+            .map(|(_, name)| *name)
+    };
+
+    match (find(&BROWSERS), find(&OPERATING_SYSTEMS)) {
+        (Some(browser), Some(os)) => format!("{} on {}", browser, os),
+        (Some(name), None) | (None, Some(name)) => name.to_string(),
+        (None, None) => String::from("Unknown device"),
+    }
+}
@@ -1,3 +1,6 @@
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use sha2::{Digest, Sha256};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 // Expiration time
@@ -8,3 +11,49 @@ pub fn create_exp(time: usize) -> usize {
         .as_secs() as usize)
         + time
 }
+
+/// create a random opaque token
+pub fn create_random_token(length: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(length)
+        .map(char::from)
+        .collect()
+}
+
+/// hash a opaque token with SHA-256, the hash is stored instead of the token
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// escape the special characters of a regular expression, to search the text literally
+pub fn escape_regex(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\.+*?()|[]{}^$-".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// The lockout time of the exponential backoff, `base` seconds doubled by every attempt over the limit
+///
+/// `exceeded` is the number of the attempts over the limit, the time is `max` seconds at most
+pub fn backoff_time(exceeded: i64, base: i64, max: i64) -> i64 {
+    base.saturating_mul(1 << exceeded.clamp(0, 32)).min(max)
+}
+
+/// The skip and the limit of the page, the page starts from 1
+pub fn page_options(
+    page: Option<u64>,
+    limit: Option<i64>,
+    default_limit: i64,
+    max_limit: i64,
+) -> (u64, i64) {
+    let limit = limit.unwrap_or(default_limit).clamp(1, max_limit);
+    let page = page.unwrap_or(1).max(1);
+
+    (page.saturating_sub(1).saturating_mul(limit as u64), limit)
+}
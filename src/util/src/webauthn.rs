@@ -0,0 +1,312 @@
+use ciborium::value::Value;
+use rand::RngCore;
+use ring::signature::{
+    RsaPublicKeyComponents, UnparsedPublicKey, ECDSA_P256_SHA256_ASN1, ED25519,
+    RSA_PKCS1_2048_8192_SHA256,
+};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+/// COSE algorithm ES256 (ECDSA P-256 with SHA-256)
+pub const COSE_ALG_ES256: i64 = -7;
+/// COSE algorithm EdDSA (Ed25519)
+pub const COSE_ALG_EDDSA: i64 = -8;
+/// COSE algorithm RS256 (RSASSA-PKCS1-v1_5 with SHA-256)
+pub const COSE_ALG_RS256: i64 = -257;
+
+/// User present
+const FLAG_UP: u8 = 0x01;
+/// User verified
+const FLAG_UV: u8 = 0x04;
+/// Attested credential data included
+const FLAG_AT: u8 = 0x40;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum WebAuthnError {
+    /// The data can't be decoded
+    InvalidData,
+    /// The client data type, challenge or origin does not match
+    ClientDataMismatch,
+    /// The authenticator data is not created for this relying party
+    RpIdMismatch,
+    /// The user is not present or not verified
+    UserNotVerified,
+    /// The public key algorithm is not supported
+    UnsupportedAlgorithm,
+    /// The signature is invalid
+    InvalidSignature,
+    /// The signature counter did not increase, the authenticator may be cloned
+    SignCountMismatch,
+}
+
+#[derive(Deserialize)]
+struct ClientData {
+    #[serde(rename = "type")]
+    ceremony_type: String,
+    challenge: String,
+    origin: String,
+}
+
+/// The parsed authenticator data
+pub struct AuthenticatorData {
+    pub rp_id_hash: Vec<u8>,
+    pub flags: u8,
+    pub sign_count: u32,
+    pub credential: Option<Credential>,
+}
+
+/// A credential created by the authenticator
+pub struct Credential {
+    pub credential_id: Vec<u8>,
+    /// The COSE encoded public key
+    pub public_key: Vec<u8>,
+    pub sign_count: u32,
+}
+
+/// The relying party options of the ceremonies
+pub struct RelyingParty<'a> {
+    pub id: &'a str,
+    pub origins: &'a [String],
+}
+
+/// create a random challenge (base64url)
+pub fn create_challenge() -> String {
+    let mut challenge = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut challenge);
+
+    base64_url_encode(&challenge)
+}
+
+pub fn base64_url_encode(data: &[u8]) -> String {
+    base64::encode_config(data, base64::URL_SAFE_NO_PAD)
+}
+
+pub fn base64_url_decode(data: &str) -> Result<Vec<u8>, WebAuthnError> {
+    base64::decode_config(data, base64::URL_SAFE_NO_PAD).map_err(|_| WebAuthnError::InvalidData)
+}
+
+/// verify the registration ceremony (`navigator.credentials.create()`)
+///
+/// The attestation statement is not verified, the attestation is requested as `none`.
+///
+/// return the created [`Credential`]
+pub fn verify_registration(
+    rp: &RelyingParty,
+    challenge: &str,
+    client_data_json: &[u8],
+    attestation_object: &[u8],
+    require_user_verification: bool,
+) -> Result<Credential, WebAuthnError> {
+    verify_client_data(rp, "webauthn.create", challenge, client_data_json)?;
+
+    let authenticator_data = parse_attestation_object(attestation_object)?;
+    verify_authenticator_data(rp, &authenticator_data, require_user_verification)?;
+
+    let mut credential = authenticator_data
+        .credential
+        .ok_or(WebAuthnError::InvalidData)?;
+    credential.sign_count = authenticator_data.sign_count;
+
+    // Check the public key can be used.
+    cose_algorithm(&credential.public_key)?;
+
+    Ok(credential)
+}
+
+/// verify the authentication ceremony (`navigator.credentials.get()`)
+///
+/// return the new signature counter
+#[allow(clippy::too_many_arguments)]
+pub fn verify_authentication(
+    rp: &RelyingParty,
+    challenge: &str,
+    public_key: &[u8],
+    sign_count: u32,
+    client_data_json: &[u8],
+    authenticator_data: &[u8],
+    signature: &[u8],
+    require_user_verification: bool,
+) -> Result<u32, WebAuthnError> {
+    verify_client_data(rp, "webauthn.get", challenge, client_data_json)?;
+
+    let parsed_authenticator_data = parse_authenticator_data(authenticator_data)?;
+    verify_authenticator_data(rp, &parsed_authenticator_data, require_user_verification)?;
+
+    let mut message = authenticator_data.to_vec();
+    message.extend_from_slice(&Sha256::digest(client_data_json));
+    verify_signature(public_key, &message, signature)?;
+
+    // The counter is always 0 if the authenticator does not support it.
+    let new_sign_count = parsed_authenticator_data.sign_count;
+    if (new_sign_count != 0 || sign_count != 0) && new_sign_count <= sign_count {
+        return Err(WebAuthnError::SignCountMismatch);
+    }
+
+    Ok(new_sign_count)
+}
+
+fn verify_client_data(
+    rp: &RelyingParty,
+    ceremony_type: &str,
+    challenge: &str,
+    client_data_json: &[u8],
+) -> Result<(), WebAuthnError> {
+    let client_data: ClientData =
+        serde_json::from_slice(client_data_json).map_err(|_| WebAuthnError::InvalidData)?;
+
+    if client_data.ceremony_type != ceremony_type
+        || client_data.challenge != challenge
+        || !rp.origins.contains(&client_data.origin)
+    {
+        return Err(WebAuthnError::ClientDataMismatch);
+    }
+
+    Ok(())
+}
+
+fn verify_authenticator_data(
+    rp: &RelyingParty,
+    authenticator_data: &AuthenticatorData,
+    require_user_verification: bool,
+) -> Result<(), WebAuthnError> {
+    if authenticator_data.rp_id_hash != Sha256::digest(rp.id.as_bytes()).to_vec() {
+        return Err(WebAuthnError::RpIdMismatch);
+    }
+
+    if authenticator_data.flags & FLAG_UP == 0
+        || (require_user_verification && authenticator_data.flags & FLAG_UV == 0)
+    {
+        return Err(WebAuthnError::UserNotVerified);
+    }
+
+    Ok(())
+}
+
+/// parse the attestation object, return the authenticator data of it
+pub fn parse_attestation_object(
+    attestation_object: &[u8],
+) -> Result<AuthenticatorData, WebAuthnError> {
+    let value: Value =
+        ciborium::de::from_reader(attestation_object).map_err(|_| WebAuthnError::InvalidData)?;
+
+    match map_get(&value, &Value::Text("authData".to_string())) {
+        Some(Value::Bytes(authenticator_data)) => parse_authenticator_data(authenticator_data),
+        _ => Err(WebAuthnError::InvalidData),
+    }
+}
+
+/// parse the authenticator data
+///
+/// ```text
+/// rpIdHash (32) | flags (1) | signCount (4) | attestedCredentialData (if AT) | extensions
+/// attestedCredentialData: aaguid (16) | credentialIdLength (2) | credentialId | credentialPublicKey (COSE)
+/// ```
+pub fn parse_authenticator_data(data: &[u8]) -> Result<AuthenticatorData, WebAuthnError> {
+    if data.len() < 37 {
+        return Err(WebAuthnError::InvalidData);
+    }
+
+    let flags = data[32];
+    let sign_count = u32::from_be_bytes([data[33], data[34], data[35], data[36]]);
+
+    let credential = if flags & FLAG_AT != 0 {
+        let rest = data.get(37 + 16..).ok_or(WebAuthnError::InvalidData)?;
+        if rest.len() < 2 {
+            return Err(WebAuthnError::InvalidData);
+        }
+
+        let credential_id_length = u16::from_be_bytes([rest[0], rest[1]]) as usize;
+        let credential_id = rest
+            .get(2..2 + credential_id_length)
+            .ok_or(WebAuthnError::InvalidData)?;
+
+        // The length of the COSE key is only known after decoding it.
+        let public_key_data = &rest[2 + credential_id_length..];
+        let mut reader = public_key_data;
+        ciborium::de::from_reader::<Value, _>(&mut reader)
+            .map_err(|_| WebAuthnError::InvalidData)?;
+        let public_key_length = public_key_data.len() - reader.len();
+
+        Some(Credential {
+            credential_id: credential_id.to_vec(),
+            public_key: public_key_data[..public_key_length].to_vec(),
+            sign_count,
+        })
+    } else {
+        None
+    };
+
+    Ok(AuthenticatorData {
+        rp_id_hash: data[..32].to_vec(),
+        flags,
+        sign_count,
+        credential,
+    })
+}
+
+/// get the algorithm of the COSE key
+fn cose_algorithm(public_key: &[u8]) -> Result<i64, WebAuthnError> {
+    let key: Value =
+        ciborium::de::from_reader(public_key).map_err(|_| WebAuthnError::InvalidData)?;
+
+    match cose_integer(&key, 3) {
+        Some(alg @ (COSE_ALG_ES256 | COSE_ALG_EDDSA | COSE_ALG_RS256)) => Ok(alg),
+        _ => Err(WebAuthnError::UnsupportedAlgorithm),
+    }
+}
+
+/// verify the signature of the message by the COSE key
+pub fn verify_signature(
+    public_key: &[u8],
+    message: &[u8],
+    signature: &[u8],
+) -> Result<(), WebAuthnError> {
+    let key: Value =
+        ciborium::de::from_reader(public_key).map_err(|_| WebAuthnError::InvalidData)?;
+
+    let result = match cose_algorithm(public_key)? {
+        COSE_ALG_ES256 => {
+            let x = cose_bytes(&key, -2)?;
+            let y = cose_bytes(&key, -3)?;
+
+            // The uncompressed point
+            let mut point = vec![0x04];
+            point.extend_from_slice(x);
+            point.extend_from_slice(y);
+
+            UnparsedPublicKey::new(&ECDSA_P256_SHA256_ASN1, point).verify(message, signature)
+        }
+        COSE_ALG_EDDSA => {
+            UnparsedPublicKey::new(&ED25519, cose_bytes(&key, -2)?).verify(message, signature)
+        }
+        COSE_ALG_RS256 => RsaPublicKeyComponents {
+            n: cose_bytes(&key, -1)?,
+            e: cose_bytes(&key, -2)?,
+        }
+        .verify(&RSA_PKCS1_2048_8192_SHA256, message, signature),
+        _ => return Err(WebAuthnError::UnsupportedAlgorithm),
+    };
+
+    result.map_err(|_| WebAuthnError::InvalidSignature)
+}
+
+fn map_get<'a>(map: &'a Value, key: &Value) -> Option<&'a Value> {
+    match map {
+        Value::Map(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+        _ => None,
+    }
+}
+
+fn cose_integer(key: &Value, label: i64) -> Option<i64> {
+    match map_get(key, &Value::Integer(label.into())) {
+        Some(Value::Integer(value)) => i64::try_from(i128::from(*value)).ok(),
+        _ => None,
+    }
+}
+
+fn cose_bytes(key: &Value, label: i64) -> Result<&[u8], WebAuthnError> {
+    match map_get(key, &Value::Integer(label.into())) {
+        Some(Value::Bytes(value)) => Ok(value),
+        _ => Err(WebAuthnError::InvalidData),
+    }
+}
@@ -1,8 +1,12 @@
-use database::model::auth::user::ConnectType;
-use util::oauth::OAuthData;
+use util::oauth::{
+    create_code_challenge, create_code_verifier, Facebook, Google, OAuthData, TaiwanCloudEducation,
+};
 
 const CLIENT_ID: &str = "123";
 const CLIENT_SECRET: &str = "abc";
+/// The example of RFC 7636 Appendix B
+const CODE_VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+const CODE_CHALLENGE: &str = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
 
 #[test]
 fn google_oauth2_get_auth_url_test() {
@@ -10,13 +14,13 @@ fn google_oauth2_get_auth_url_test() {
     let client_id = CLIENT_ID.to_string();
 
     let oauth = OAuthData {
-        account_type: ConnectType::Google,
+        provider: &Google,
         client_secret,
         client_id,
         redirect_uri: "http://127.0.0.1:8000/login".to_string(),
     };
 
-    assert_eq!("https://accounts.google.com/o/oauth2/auth?client_id=123&response_type=code&scope=https%3A%2F%2Fwww.googleapis.com%2Fauth%2Fuserinfo.profile%20https%3A%2F%2Fwww.googleapis.com%2Fauth%2Fuserinfo.email&redirect_uri=http%3A%2F%2F127.0.0.1%3A8000%2Flogin", oauth.get_auth_url())
+    assert_eq!("https://accounts.google.com/o/oauth2/auth?client_id=123&response_type=code&scope=https%3A%2F%2Fwww.googleapis.com%2Fauth%2Fuserinfo.profile%20https%3A%2F%2Fwww.googleapis.com%2Fauth%2Fuserinfo.email&redirect_uri=http%3A%2F%2F127.0.0.1%3A8000%2Flogin&state=xyz&code_challenge=E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM&code_challenge_method=S256", oauth.get_auth_url("xyz", CODE_CHALLENGE))
 }
 
 #[test]
@@ -25,11 +29,42 @@ fn facebook_oauth2_get_auth_url_test() {
     let client_id = CLIENT_ID.to_string();
 
     let oauth = OAuthData {
-        account_type: ConnectType::Facebook,
+        provider: &Facebook,
         client_secret,
         client_id,
         redirect_uri: "http://127.0.0.1:8000/login".to_string(),
     };
 
-    assert_eq!("https://www.facebook.com/dialog/oauth?client_id=123&response_type=code&scope=public_profile%2Cemail&redirect_uri=http%3A%2F%2F127.0.0.1%3A8000%2Flogin", oauth.get_auth_url())
+    assert_eq!("https://www.facebook.com/dialog/oauth?client_id=123&response_type=code&scope=public_profile%2Cemail&redirect_uri=http%3A%2F%2F127.0.0.1%3A8000%2Flogin&state=xyz&code_challenge=E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM&code_challenge_method=S256", oauth.get_auth_url("xyz", CODE_CHALLENGE))
+}
+
+#[test]
+fn taiwan_cloud_education_oauth2_get_auth_url_test() {
+    let client_secret = CLIENT_SECRET.to_string();
+    let client_id = CLIENT_ID.to_string();
+
+    let oauth = OAuthData {
+        provider: &TaiwanCloudEducation,
+        client_secret,
+        client_id,
+        redirect_uri: "http://127.0.0.1:8000/login".to_string(),
+    };
+
+    assert_eq!("https://oidc.tanet.edu.tw/oidc/v1/azp?client_id=123&response_type=code&scope=openid%20email%20profile%20eduinfo&redirect_uri=http%3A%2F%2F127.0.0.1%3A8000%2Flogin&state=xyz&code_challenge=E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM&code_challenge_method=S256", oauth.get_auth_url("xyz", CODE_CHALLENGE))
+}
+
+#[test]
+fn pkce_test() {
+    assert_eq!(create_code_challenge(CODE_VERIFIER), CODE_CHALLENGE);
+
+    let code_verifier = create_code_verifier(CLIENT_SECRET.as_bytes(), "xyz");
+    assert_eq!(code_verifier.len(), 43);
+    assert_eq!(
+        code_verifier,
+        create_code_verifier(CLIENT_SECRET.as_bytes(), "xyz")
+    );
+    assert_ne!(
+        code_verifier,
+        create_code_verifier(CLIENT_SECRET.as_bytes(), "abc")
+    );
 }
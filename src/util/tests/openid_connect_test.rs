@@ -0,0 +1,126 @@
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use ring::rand::SystemRandom;
+use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+use serde::Serialize;
+use util::oauth::{OpenIdConfiguration, OpenIdConnect};
+use util::util::create_exp;
+use util::webauthn::base64_url_encode;
+
+const ISSUER: &str = "https://sso.example.edu.tw/realms/school";
+const CLIENT_ID: &str = "lipoic";
+
+#[derive(Serialize)]
+struct TestClaims {
+    iss: String,
+    aud: String,
+    exp: usize,
+    sub: String,
+    email: String,
+    email_verified: bool,
+}
+
+/// create a ES256 key, return the encoding key and the JWKS of the key
+fn create_key(kid: &str) -> (EncodingKey, JwkSet) {
+    let pkcs8 =
+        EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &SystemRandom::new())
+            .unwrap();
+    let key_pair =
+        EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref()).unwrap();
+    // The uncompressed point: 0x04 | x (32) | y (32)
+    let point = key_pair.public_key().as_ref();
+
+    let jwks = serde_json::from_value(serde_json::json!({
+        "keys": [{
+            "kty": "EC",
+            "crv": "P-256",
+            "alg": "ES256",
+            "use": "sig",
+            "kid": kid,
+            "x": base64_url_encode(&point[1..33]),
+            "y": base64_url_encode(&point[33..]),
+        }]
+    }))
+    .unwrap();
+
+    (EncodingKey::from_ec_der(pkcs8.as_ref()), jwks)
+}
+
+fn create_provider(jwks: JwkSet) -> OpenIdConnect {
+    OpenIdConnect::new(
+        "school",
+        CLIENT_ID,
+        OpenIdConfiguration {
+            issuer: ISSUER.to_string(),
+            authorization_endpoint: format!("{}/protocol/openid-connect/auth", ISSUER),
+            token_endpoint: format!("{}/protocol/openid-connect/token", ISSUER),
+            userinfo_endpoint: None,
+            // The keys are not refreshed in the tests
+            jwks_uri: "http://127.0.0.1:1/certs".to_string(),
+        },
+        jwks,
+    )
+}
+
+fn create_id_token(encoding_key: &EncodingKey, kid: &str, aud: &str) -> String {
+    let mut header = Header::new(Algorithm::ES256);
+    header.kid = Some(kid.to_string());
+
+    encode(
+        &header,
+        &TestClaims {
+            iss: ISSUER.to_string(),
+            aud: aud.to_string(),
+            exp: create_exp(60),
+            sub: "f:1234".to_string(),
+            email: "student@example.edu.tw".to_string(),
+            email_verified: true,
+        },
+        encoding_key,
+    )
+    .unwrap()
+}
+
+#[tokio::test]
+async fn verify_id_token_test() {
+    let (encoding_key, jwks) = create_key("key-1");
+    let provider = create_provider(jwks);
+
+    let claims = provider
+        .verify_id_token(&create_id_token(&encoding_key, "key-1", CLIENT_ID))
+        .await
+        .unwrap();
+
+    assert_eq!(claims.sub, "f:1234");
+    assert_eq!(claims.email.as_deref(), Some("student@example.edu.tw"));
+    assert_eq!(claims.email_verified, Some(true));
+}
+
+#[tokio::test]
+async fn verify_id_token_audience_test() {
+    let (encoding_key, jwks) = create_key("key-1");
+    let provider = create_provider(jwks);
+
+    assert!(provider
+        .verify_id_token(&create_id_token(&encoding_key, "key-1", "another-client"))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn verify_id_token_key_test() {
+    let (_, jwks) = create_key("key-1");
+    let (another_encoding_key, _) = create_key("key-1");
+    let provider = create_provider(jwks);
+
+    // Signed by another key
+    assert!(provider
+        .verify_id_token(&create_id_token(&another_encoding_key, "key-1", CLIENT_ID))
+        .await
+        .is_err());
+    // Unknown key id
+    assert!(provider
+        .verify_id_token(&create_id_token(&another_encoding_key, "key-2", CLIENT_ID))
+        .await
+        .is_err());
+}
@@ -0,0 +1,9 @@
+use util::password::is_strong_password;
+
+#[test]
+fn strong_password_test() {
+    assert!(is_strong_password("lipoic2022"));
+    assert!(!is_strong_password("abc123"));
+    assert!(!is_strong_password("abcdefghijk"));
+    assert!(!is_strong_password("12345678"));
+}
@@ -0,0 +1,40 @@
+use std::time::{Duration, Instant};
+use util::rate_limit::{path_matches, TokenBucket};
+
+#[test]
+fn token_bucket_burst_test() {
+    let now = Instant::now();
+    let mut bucket = TokenBucket::new(3, now);
+
+    for _ in 0..3 {
+        assert_eq!(bucket.take(3, 0.5, now), Ok(()));
+    }
+    // A token is refilled every 2 seconds.
+    assert_eq!(bucket.take(3, 0.5, now), Err(2));
+}
+
+#[test]
+fn token_bucket_refill_test() {
+    let now = Instant::now();
+    let mut bucket = TokenBucket::new(2, now);
+
+    assert_eq!(bucket.take(2, 1.0, now), Ok(()));
+    assert_eq!(bucket.take(2, 1.0, now), Ok(()));
+    assert!(bucket.take(2, 1.0, now).is_err());
+
+    let later = now + Duration::from_secs(1);
+    assert_eq!(bucket.take(2, 1.0, later), Ok(()));
+    assert_eq!(bucket.updated(), later);
+
+    // The tokens are never more than the capacity.
+    let much_later = later + Duration::from_secs(60);
+    assert_eq!(bucket.tokens_at(2, 1.0, much_later), 2.0);
+}
+
+#[test]
+fn path_matches_test() {
+    assert!(path_matches("/user/login", "/user/login"));
+    assert!(!path_matches("/user/login", "/user/login/mfa"));
+    assert!(path_matches("/user/login*", "/user/login/mfa"));
+    assert!(!path_matches("/user/login*", "/user/logout"));
+}
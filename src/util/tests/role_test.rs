@@ -0,0 +1,28 @@
+use database::model::auth::user::Role;
+use util::role::{role_permissions, roles_include, Permission};
+
+#[test]
+fn role_permissions_test() {
+    assert!(role_permissions(Role::Admin).contains(&Permission::ManageUsers));
+    assert!(!role_permissions(Role::SchoolAdmin).contains(&Permission::ManageUsers));
+    assert_eq!(
+        role_permissions(Role::Teacher),
+        &[Permission::ManageCourses]
+    );
+}
+
+#[test]
+fn roles_include_test() {
+    // A role includes the roles with fewer permissions.
+    assert!(roles_include(&[Role::Admin], Role::SchoolAdmin));
+    assert!(roles_include(&[Role::Admin], Role::Teacher));
+    assert!(roles_include(&[Role::SchoolAdmin], Role::Teacher));
+
+    assert!(!roles_include(&[Role::SchoolAdmin], Role::Admin));
+    assert!(!roles_include(&[Role::Teacher], Role::SchoolAdmin));
+    assert!(roles_include(
+        &[Role::Teacher, Role::SchoolAdmin],
+        Role::SchoolAdmin
+    ));
+    assert!(!roles_include(&[], Role::Teacher));
+}
@@ -0,0 +1,50 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use util::totp::{
+    create_totp_code, create_totp_secret, create_totp_uri, verify_totp_code, verify_totp_step,
+};
+
+/// The base32 of "12345678901234567890", the secret of the RFC 6238 test vectors
+const SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+#[test]
+fn create_totp_code_test() {
+    assert_eq!(create_totp_code(SECRET, 59).unwrap(), "287082");
+    assert_eq!(create_totp_code(SECRET, 1111111109).unwrap(), "081804");
+    assert_eq!(create_totp_code(SECRET, 1234567890).unwrap(), "005924");
+    assert!(create_totp_code("!", 59).is_none());
+}
+
+#[test]
+fn verify_totp_code_test() {
+    let secret = create_totp_secret();
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    assert!(verify_totp_code(
+        &secret,
+        &create_totp_code(&secret, now).unwrap()
+    ));
+    assert!(!verify_totp_code(
+        &secret,
+        &create_totp_code(&secret, now - 60 * 10).unwrap()
+    ));
+}
+
+#[test]
+fn verify_totp_step_test() {
+    assert_eq!(verify_totp_step(SECRET, "287082", 59), Some(1));
+    // The code of the previous step is still accepted, with the step it belongs to.
+    assert_eq!(verify_totp_step(SECRET, "287082", 89), Some(1));
+    assert_eq!(verify_totp_step(SECRET, "287082", 59 + 60 * 10), None);
+    assert_eq!(verify_totp_step(SECRET, "000000", 59), None);
+}
+
+#[test]
+fn create_totp_uri_test() {
+    assert_eq!(
+        create_totp_uri(SECRET, "abc@gmail.com", "Lipoic"),
+        "otpauth://totp/Lipoic:abc%40gmail.com?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&issuer=Lipoic&algorithm=SHA1&digits=6&period=30"
+    );
+}
@@ -0,0 +1,22 @@
+use util::user_agent::device_name;
+
+#[test]
+fn device_name_test() {
+    assert_eq!(
+        device_name("Mozilla/5.0 (X11; CrOS x86_64 14541.0.0) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/107.0.0.0 Safari/537.36"),
+        "Chrome on ChromeOS"
+    );
+    assert_eq!(
+        device_name("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/107.0.0.0 Safari/537.36 Edg/107.0.1418.42"),
+        "Edge on Windows"
+    );
+    assert_eq!(
+        device_name("Mozilla/5.0 (iPhone; CPU iPhone OS 16_1 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/16.1 Mobile/15E148 Safari/604.1"),
+        "Safari on iOS"
+    );
+    assert_eq!(
+        device_name("Mozilla/5.0 (Linux; Android 13; Pixel 7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/107.0.0.0 Mobile Safari/537.36"),
+        "Chrome on Android"
+    );
+    assert_eq!(device_name("curl/7.86.0"), "Unknown device");
+}
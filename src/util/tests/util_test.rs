@@ -0,0 +1,43 @@
+use util::util::{backoff_time, create_random_token, escape_regex, hash_token, page_options};
+
+#[test]
+fn create_random_token_test() {
+    let token = create_random_token(64);
+
+    assert_eq!(token.len(), 64);
+    assert_ne!(token, create_random_token(64));
+}
+
+#[test]
+fn hash_token_test() {
+    let token = create_random_token(64);
+
+    assert_eq!(hash_token(&token), hash_token(&token));
+    assert_ne!(hash_token(&token), token);
+}
+
+#[test]
+fn escape_regex_test() {
+    assert_eq!(escape_regex("lipoic"), "lipoic");
+    assert_eq!(escape_regex("a.b+c@lipoic.org"), "a\\.b\\+c@lipoic\\.org");
+    assert_eq!(escape_regex("(.*)"), "\\(\\.\\*\\)");
+}
+
+#[test]
+fn backoff_time_test() {
+    assert_eq!(backoff_time(0, 60, 3600), 60);
+    assert_eq!(backoff_time(1, 60, 3600), 120);
+    assert_eq!(backoff_time(5, 60, 3600), 1920);
+    assert_eq!(backoff_time(6, 60, 3600), 3600);
+    // The time doesn't overflow after many attempts.
+    assert_eq!(backoff_time(1000, 60, 3600), 3600);
+}
+
+#[test]
+fn page_options_test() {
+    assert_eq!(page_options(None, None, 20, 100), (0, 20));
+    assert_eq!(page_options(Some(3), Some(10), 20, 100), (20, 10));
+    // The page starts from 1, and the limit is between 1 and the maximum.
+    assert_eq!(page_options(Some(0), Some(0), 20, 100), (0, 1));
+    assert_eq!(page_options(Some(2), Some(1000), 20, 100), (100, 100));
+}
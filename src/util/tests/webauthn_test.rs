@@ -0,0 +1,296 @@
+use ciborium::value::Value;
+use ring::rand::SystemRandom;
+use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+use sha2::{Digest, Sha256};
+use util::webauthn::{
+    base64_url_decode, base64_url_encode, create_challenge, verify_authentication,
+    verify_registration, RelyingParty, WebAuthnError, COSE_ALG_ES256,
+};
+
+const RP_ID: &str = "localhost";
+const ORIGIN: &str = "http://localhost:3000";
+
+/// A software authenticator with a ES256 key
+struct SoftwareAuthenticator {
+    key_pair: EcdsaKeyPair,
+    credential_id: Vec<u8>,
+    sign_count: u32,
+    /// The flags of the authenticator data, user present and user verified by default
+    flags: u8,
+}
+
+impl SoftwareAuthenticator {
+    fn new() -> Self {
+        let pkcs8 =
+            EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &SystemRandom::new())
+                .unwrap();
+
+        SoftwareAuthenticator {
+            key_pair: EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref())
+                .unwrap(),
+            credential_id: b"software-authenticator".to_vec(),
+            sign_count: 0,
+            flags: 0x01 | 0x04,
+        }
+    }
+
+    fn cose_key(&self) -> Vec<u8> {
+        // The uncompressed point: 0x04 | x (32) | y (32)
+        let point = self.key_pair.public_key().as_ref();
+        let key = Value::Map(vec![
+            (Value::Integer(1.into()), Value::Integer(2.into())),
+            (
+                Value::Integer(3.into()),
+                Value::Integer(COSE_ALG_ES256.into()),
+            ),
+            (Value::Integer((-1).into()), Value::Integer(1.into())),
+            (
+                Value::Integer((-2).into()),
+                Value::Bytes(point[1..33].to_vec()),
+            ),
+            (
+                Value::Integer((-3).into()),
+                Value::Bytes(point[33..].to_vec()),
+            ),
+        ]);
+
+        let mut data = vec![];
+        ciborium::ser::into_writer(&key, &mut data).unwrap();
+        data
+    }
+
+    fn client_data_json(&self, ceremony_type: &str, challenge: &str) -> Vec<u8> {
+        format!(
+            r#"{{"type":"{}","challenge":"{}","origin":"{}","crossOrigin":false}}"#,
+            ceremony_type, challenge, ORIGIN
+        )
+        .into_bytes()
+    }
+
+    fn authenticator_data(&self, rp_id: &str, attested: bool) -> Vec<u8> {
+        let mut data = Sha256::digest(rp_id.as_bytes()).to_vec();
+        data.push(if attested {
+            self.flags | 0x40
+        } else {
+            self.flags
+        });
+        data.extend_from_slice(&self.sign_count.to_be_bytes());
+
+        if attested {
+            data.extend_from_slice(&[0u8; 16]);
+            data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+            data.extend_from_slice(&self.credential_id);
+            data.extend_from_slice(&self.cose_key());
+        }
+
+        data
+    }
+
+    /// `navigator.credentials.create()`
+    fn create(&self, rp_id: &str, challenge: &str) -> (Vec<u8>, Vec<u8>) {
+        let attestation_object = Value::Map(vec![
+            (
+                Value::Text("fmt".to_string()),
+                Value::Text("none".to_string()),
+            ),
+            (Value::Text("attStmt".to_string()), Value::Map(vec![])),
+            (
+                Value::Text("authData".to_string()),
+                Value::Bytes(self.authenticator_data(rp_id, true)),
+            ),
+        ]);
+
+        let mut data = vec![];
+        ciborium::ser::into_writer(&attestation_object, &mut data).unwrap();
+
+        (self.client_data_json("webauthn.create", challenge), data)
+    }
+
+    /// `navigator.credentials.get()`
+    fn get(&mut self, rp_id: &str, challenge: &str) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+        self.sign_count += 1;
+
+        let client_data_json = self.client_data_json("webauthn.get", challenge);
+        let authenticator_data = self.authenticator_data(rp_id, false);
+
+        let mut message = authenticator_data.clone();
+        message.extend_from_slice(&Sha256::digest(&client_data_json));
+        let signature = self
+            .key_pair
+            .sign(&SystemRandom::new(), &message)
+            .unwrap()
+            .as_ref()
+            .to_vec();
+
+        (client_data_json, authenticator_data, signature)
+    }
+}
+
+fn relying_party(origins: &[String]) -> RelyingParty<'_> {
+    RelyingParty { id: RP_ID, origins }
+}
+
+#[test]
+fn base64_url_test() {
+    let challenge = create_challenge();
+
+    assert_eq!(base64_url_decode(&challenge).unwrap().len(), 32);
+    assert_eq!(base64_url_encode(&[251, 255]), "-_8");
+}
+
+#[test]
+fn registration_test() {
+    let origins = vec![ORIGIN.to_string()];
+    let authenticator = SoftwareAuthenticator::new();
+    let challenge = create_challenge();
+    let (client_data_json, attestation_object) = authenticator.create(RP_ID, &challenge);
+
+    let credential = verify_registration(
+        &relying_party(&origins),
+        &challenge,
+        &client_data_json,
+        &attestation_object,
+        true,
+    )
+    .unwrap();
+
+    assert_eq!(credential.credential_id, authenticator.credential_id);
+    assert_eq!(credential.public_key, authenticator.cose_key());
+    assert_eq!(credential.sign_count, 0);
+
+    // Another challenge
+    assert_eq!(
+        verify_registration(
+            &relying_party(&origins),
+            &create_challenge(),
+            &client_data_json,
+            &attestation_object,
+            true,
+        )
+        .err(),
+        Some(WebAuthnError::ClientDataMismatch)
+    );
+
+    // Another relying party
+    let (client_data_json, attestation_object) = authenticator.create("example.com", &challenge);
+    assert_eq!(
+        verify_registration(
+            &relying_party(&origins),
+            &challenge,
+            &client_data_json,
+            &attestation_object,
+            true,
+        )
+        .err(),
+        Some(WebAuthnError::RpIdMismatch)
+    );
+}
+
+#[test]
+fn authentication_test() {
+    let origins = vec![ORIGIN.to_string()];
+    let mut authenticator = SoftwareAuthenticator::new();
+    let public_key = authenticator.cose_key();
+
+    let challenge = create_challenge();
+    let (client_data_json, authenticator_data, signature) = authenticator.get(RP_ID, &challenge);
+
+    let sign_count = verify_authentication(
+        &relying_party(&origins),
+        &challenge,
+        &public_key,
+        0,
+        &client_data_json,
+        &authenticator_data,
+        &signature,
+        true,
+    )
+    .unwrap();
+    assert_eq!(sign_count, 1);
+
+    // Replay the same assertion
+    assert_eq!(
+        verify_authentication(
+            &relying_party(&origins),
+            &challenge,
+            &public_key,
+            sign_count,
+            &client_data_json,
+            &authenticator_data,
+            &signature,
+            true,
+        )
+        .err(),
+        Some(WebAuthnError::SignCountMismatch)
+    );
+
+    // Another origin
+    assert_eq!(
+        verify_authentication(
+            &relying_party(&["https://lipoic.org".to_string()]),
+            &challenge,
+            &public_key,
+            0,
+            &client_data_json,
+            &authenticator_data,
+            &signature,
+            true,
+        )
+        .err(),
+        Some(WebAuthnError::ClientDataMismatch)
+    );
+
+    // Another key
+    assert_eq!(
+        verify_authentication(
+            &relying_party(&origins),
+            &challenge,
+            &SoftwareAuthenticator::new().cose_key(),
+            0,
+            &client_data_json,
+            &authenticator_data,
+            &signature,
+            true,
+        )
+        .err(),
+        Some(WebAuthnError::InvalidSignature)
+    );
+}
+
+#[test]
+fn user_verification_test() {
+    let origins = vec![ORIGIN.to_string()];
+    let mut authenticator = SoftwareAuthenticator::new();
+    // User present only
+    authenticator.flags = 0x01;
+    let public_key = authenticator.cose_key();
+
+    let challenge = create_challenge();
+    let (client_data_json, authenticator_data, signature) = authenticator.get(RP_ID, &challenge);
+
+    assert_eq!(
+        verify_authentication(
+            &relying_party(&origins),
+            &challenge,
+            &public_key,
+            0,
+            &client_data_json,
+            &authenticator_data,
+            &signature,
+            true,
+        )
+        .err(),
+        Some(WebAuthnError::UserNotVerified)
+    );
+    assert!(verify_authentication(
+        &relying_party(&origins),
+        &challenge,
+        &public_key,
+        0,
+        &client_data_json,
+        &authenticator_data,
+        &signature,
+        false,
+    )
+    .is_ok());
+}